@@ -0,0 +1,510 @@
+#![allow(clippy::unwrap_used, clippy::missing_panics_doc)]
+//! Compares every manager and channel, with commands of many sizes sent by one or many
+//! producers
+//!
+//! Keyed pools are sent every command with a new key, and detached managers hand their results
+//! to a sink that's waited on like a result queue.
+//!
+//! Throughput and mean latency are reported by criterion, so `--save-baseline` and
+//! `--baseline` catch regressions. Latency percentiles are printed, and written to
+//! `target/criterion/latency/<size>/percentiles.csv`.
+use criterion::measurement::WallTime;
+use criterion::{
+    BenchmarkGroup, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main,
+};
+use crossbeam_channel as mpmc;
+use std::cell::{Cell, RefCell};
+use std::hint::black_box;
+use std::io::Write;
+use std::sync::LazyLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use supera::channel::{Channel, Crossbeam, SharedChannel, Spsc, Std};
+use supera::detached::{DetachedAPI, ResultSink};
+use supera::error::SendError;
+use supera::oneshot::QueuedCommand;
+use supera::oneshot_pool::OneShotPoolAPI;
+use supera::oneshot_single::OneShotChanAPI;
+use supera::queue_envelope::{CommandId, EnvelopePoolAPI, Tagged};
+use supera::queue_keyed::KeyedPoolAPI;
+use supera::queue_pool::PoolQueueAPI;
+use supera::queue_single::SingleQueueChanAPI;
+use supera::submit::{EnvelopeSubmitter, KeyedSubmitter, LinkSubmitter, Submit, Submitter};
+use supera::{ChanSend, CommandRunner, ResultQueue, RunnerConfig};
+
+#[cfg(feature = "flume")]
+use supera::channel::Flume;
+
+/// Commands sent by each round of the throughput benches
+const ROUND: usize = 1024;
+const PRODUCERS: [usize; 2] = [1, 4];
+/// Runners of the keyed pools
+const KEYED: usize = 4;
+
+/// Sums `SIZE` bytes, so commands of different sizes can be compared
+#[derive(Debug, Clone, Copy)]
+enum Work<const SIZE: usize> {
+    Sum([u8; SIZE]),
+    Stop,
+}
+
+impl<const SIZE: usize> Work<SIZE> {
+    fn new() -> Self {
+        Self::Sum(black_box([1; SIZE]))
+    }
+}
+
+impl<const SIZE: usize> supera::SimpleStop for Work<SIZE> {
+    fn make_stop_command() -> Self {
+        Self::Stop
+    }
+}
+
+impl<const SIZE: usize> supera::Command for Work<SIZE> {
+    type Result = u64;
+    fn execute(self) -> supera::ActionResult<u64> {
+        match self {
+            Self::Sum(bytes) => {
+                supera::ActionResult::Normal(bytes.iter().map(|&b| u64::from(b)).sum())
+            }
+            Self::Stop => supera::ActionResult::Stop,
+        }
+    }
+}
+
+/// Drives any manager the same way, so their results are comparable
+trait Manager<const SIZE: usize>: Submit<Work<SIZE>> + Sized {
+    /// # Safety
+    /// See [`CommandRunner::new`], the manager is stopped by [`Manager::stop`].
+    unsafe fn start() -> Self;
+    /// Waits for the results of the commands that gave `acks`
+    fn wait(&self, acks: impl IntoIterator<Item = Self::Ack>);
+    fn stop(self);
+}
+
+/// A [`Manager`] that many threads can send to
+trait Shared<const SIZE: usize>: Manager<SIZE> {
+    type Producer: Submit<Work<SIZE>, Ack = Self::Ack> + Send;
+    fn producer(&self) -> Self::Producer;
+}
+
+impl<const SIZE: usize, Ch: Channel> Manager<SIZE> for SingleQueueChanAPI<Work<SIZE>, Ch> {
+    unsafe fn start() -> Self {
+        unsafe { Self::new() }
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = ()>) {
+        for () in acks {
+            self.results().recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.close();
+    }
+}
+
+impl<const SIZE: usize, Ch: Channel> Shared<SIZE> for SingleQueueChanAPI<Work<SIZE>, Ch>
+where
+    Ch::Sender<Work<SIZE>>: Clone,
+{
+    type Producer = Submitter<Work<SIZE>, Ch::Sender<Work<SIZE>>>;
+    fn producer(&self) -> Self::Producer {
+        self.submitter()
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Manager<SIZE>
+    for PoolQueueAPI<Work<SIZE>, N, Ch>
+{
+    unsafe fn start() -> Self {
+        unsafe { Self::new() }
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = ()>) {
+        for () in acks {
+            self.results().recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.close();
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Shared<SIZE>
+    for PoolQueueAPI<Work<SIZE>, N, Ch>
+where
+    Ch::Sender<Work<SIZE>>: Clone,
+{
+    type Producer = Submitter<Work<SIZE>, Ch::Sender<Work<SIZE>>>;
+    fn producer(&self) -> Self::Producer {
+        self.submitter()
+    }
+}
+
+impl<const SIZE: usize, Ch: Channel> Manager<SIZE> for OneShotChanAPI<Work<SIZE>, Ch> {
+    unsafe fn start() -> Self {
+        unsafe { Self::new() }
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = Self::Ack>) {
+        for link in acks {
+            link.recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.close();
+    }
+}
+
+impl<const SIZE: usize, Ch: Channel> Shared<SIZE> for OneShotChanAPI<Work<SIZE>, Ch>
+where
+    Ch::Sender<QueuedCommand<Work<SIZE>>>: Clone,
+{
+    type Producer = LinkSubmitter<Work<SIZE>, Ch::Sender<QueuedCommand<Work<SIZE>>>>;
+    fn producer(&self) -> Self::Producer {
+        self.submitter()
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Manager<SIZE>
+    for OneShotPoolAPI<Work<SIZE>, N, Ch>
+{
+    unsafe fn start() -> Self {
+        unsafe { Self::new() }
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = Self::Ack>) {
+        for link in acks {
+            link.recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.close();
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Shared<SIZE>
+    for OneShotPoolAPI<Work<SIZE>, N, Ch>
+where
+    Ch::Sender<QueuedCommand<Work<SIZE>>>: Clone,
+{
+    type Producer = LinkSubmitter<Work<SIZE>, Ch::Sender<QueuedCommand<Work<SIZE>>>>;
+    fn producer(&self) -> Self::Producer {
+        self.submitter()
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Manager<SIZE>
+    for EnvelopePoolAPI<Work<SIZE>, N, Ch>
+{
+    unsafe fn start() -> Self {
+        unsafe { Self::new() }
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = CommandId>) {
+        for _ in acks {
+            self.recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.close();
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Shared<SIZE>
+    for EnvelopePoolAPI<Work<SIZE>, N, Ch>
+where
+    Ch::Sender<Tagged<Work<SIZE>>>: Clone,
+{
+    type Producer = EnvelopeSubmitter<Work<SIZE>, Ch::Sender<Tagged<Work<SIZE>>>>;
+    fn producer(&self) -> Self::Producer {
+        self.submitter()
+    }
+}
+
+/// Sends each command with the next key, so they're spread over every runner
+struct Keyed<S> {
+    to: S,
+    next: Cell<usize>,
+}
+
+impl<S> Keyed<S> {
+    fn new(to: S) -> Self {
+        Self {
+            to,
+            next: Cell::new(0),
+        }
+    }
+
+    fn key(&self) -> usize {
+        let key = self.next.get();
+        self.next.set(key + 1);
+        key
+    }
+}
+
+impl<const SIZE: usize, Ch: Channel> Submit<Work<SIZE>> for Keyed<KeyedPoolAPI<Work<SIZE>, Ch>> {
+    type Ack = ();
+    fn submit(&self, cmd: Work<SIZE>) -> Result<(), SendError<Work<SIZE>>> {
+        self.to.send(&self.key(), cmd)
+    }
+}
+
+impl<const SIZE: usize, S> Submit<Work<SIZE>> for Keyed<KeyedSubmitter<Work<SIZE>, S>>
+where
+    S: ChanSend<Work<SIZE>>,
+{
+    type Ack = ();
+    fn submit(&self, cmd: Work<SIZE>) -> Result<(), SendError<Work<SIZE>>> {
+        Ok(self.to.send(&self.key(), cmd)?)
+    }
+}
+
+impl<const SIZE: usize, Ch: Channel> Manager<SIZE> for Keyed<KeyedPoolAPI<Work<SIZE>, Ch>> {
+    unsafe fn start() -> Self {
+        Self::new(unsafe { KeyedPoolAPI::new(KEYED) })
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = ()>) {
+        for () in acks {
+            self.to.recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.to.close();
+    }
+}
+
+impl<const SIZE: usize, Ch: Channel> Shared<SIZE> for Keyed<KeyedPoolAPI<Work<SIZE>, Ch>>
+where
+    Ch::Sender<Work<SIZE>>: Send + Sync,
+{
+    type Producer = Keyed<KeyedSubmitter<Work<SIZE>, Ch::Sender<Work<SIZE>>>>;
+    fn producer(&self) -> Self::Producer {
+        Keyed::new(self.to.submitter())
+    }
+}
+
+/// Results of every [`DetachedAPI`], which waiting for them receives
+static DONE: LazyLock<(mpmc::Sender<u64>, mpmc::Receiver<u64>)> = LazyLock::new(mpmc::unbounded);
+
+/// Hands the results of a [`DetachedAPI`] to [`DONE`]
+struct Done;
+
+impl ResultSink<u64> for Done {
+    fn accept(&self, result: u64) {
+        DONE.0.send(result).unwrap();
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Manager<SIZE>
+    for DetachedAPI<Work<SIZE>, N, Ch>
+{
+    unsafe fn start() -> Self {
+        unsafe { Self::with_sink(Done, &RunnerConfig::default()) }
+    }
+    fn wait(&self, acks: impl IntoIterator<Item = ()>) {
+        for () in acks {
+            DONE.1.recv().unwrap();
+        }
+    }
+    fn stop(self) {
+        let _ = self.close();
+    }
+}
+
+impl<const SIZE: usize, const N: usize, Ch: SharedChannel> Shared<SIZE>
+    for DetachedAPI<Work<SIZE>, N, Ch>
+where
+    Ch::Sender<Work<SIZE>>: Clone,
+{
+    type Producer = Submitter<Work<SIZE>, Ch::Sender<Work<SIZE>>>;
+    fn producer(&self) -> Self::Producer {
+        self.submitter()
+    }
+}
+
+fn send_many<const SIZE: usize, S: Submit<Work<SIZE>>>(to: &S, n: usize) -> Vec<S::Ack> {
+    (0..n).map(|_| to.submit(Work::new()).unwrap()).collect()
+}
+
+/// Sends a round of commands from the current thread
+fn round<const SIZE: usize, M: Manager<SIZE>>(m: &M) {
+    m.wait(send_many(m, ROUND));
+}
+
+/// Splits a round between `producers` threads, the results are waited on as they finish
+fn round_from<const SIZE: usize, M: Shared<SIZE>>(m: &M, producers: usize)
+where
+    M::Ack: Send,
+{
+    thread::scope(|s| {
+        let sending: Vec<_> = (0..producers)
+            .map(|_| {
+                let p = m.producer();
+                s.spawn(move || send_many(&p, ROUND / producers))
+            })
+            .collect();
+        for acks in sending {
+            m.wait(acks.join().unwrap());
+        }
+    });
+}
+
+/// Benches a manager that can only be sent to by a single thread
+fn exclusive<const SIZE: usize, M: Manager<SIZE>>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+) {
+    let m = unsafe { M::start() };
+    group.bench_function(BenchmarkId::new(name, "1p"), |b| b.iter(|| round(&m)));
+    m.stop();
+}
+
+fn shared<const SIZE: usize, M: Shared<SIZE>>(group: &mut BenchmarkGroup<WallTime>, name: &str)
+where
+    M::Ack: Send,
+{
+    let m = unsafe { M::start() };
+    for producers in PRODUCERS {
+        let id = BenchmarkId::new(name, format!("{producers}p"));
+        if producers == 1 {
+            group.bench_function(id, |b| b.iter(|| round(&m)));
+        } else {
+            group.bench_function(id, |b| b.iter(|| round_from(&m, producers)));
+        }
+    }
+    m.stop();
+}
+
+fn throughput<const SIZE: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("throughput/{SIZE}B"));
+    group.throughput(Throughput::Elements(ROUND as u64));
+    shared::<SIZE, SingleQueueChanAPI<Work<SIZE>, Std>>(&mut group, "single-std");
+    shared::<SIZE, SingleQueueChanAPI<Work<SIZE>, Crossbeam>>(&mut group, "single-crossbeam");
+    #[cfg(feature = "flume")]
+    shared::<SIZE, SingleQueueChanAPI<Work<SIZE>, Flume>>(&mut group, "single-flume");
+    exclusive::<SIZE, SingleQueueChanAPI<Work<SIZE>, Spsc>>(&mut group, "single-spsc");
+    shared::<SIZE, PoolQueueAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, "pool1-crossbeam");
+    shared::<SIZE, PoolQueueAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, "pool4-crossbeam");
+    #[cfg(feature = "flume")]
+    shared::<SIZE, PoolQueueAPI<Work<SIZE>, 4, Flume>>(&mut group, "pool4-flume");
+    shared::<SIZE, OneShotChanAPI<Work<SIZE>, Std>>(&mut group, "oneshot-std");
+    shared::<SIZE, OneShotChanAPI<Work<SIZE>, Crossbeam>>(&mut group, "oneshot-crossbeam");
+    exclusive::<SIZE, OneShotChanAPI<Work<SIZE>, Spsc>>(&mut group, "oneshot-spsc");
+    shared::<SIZE, OneShotPoolAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, "oneshot-pool1");
+    shared::<SIZE, OneShotPoolAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, "oneshot-pool4");
+    #[cfg(feature = "flume")]
+    shared::<SIZE, OneShotPoolAPI<Work<SIZE>, 4, Flume>>(&mut group, "oneshot-pool4-flume");
+    shared::<SIZE, EnvelopePoolAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, "envelope-pool1");
+    shared::<SIZE, EnvelopePoolAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, "envelope-pool4");
+    shared::<SIZE, Keyed<KeyedPoolAPI<Work<SIZE>, Std>>>(&mut group, "keyed4-std");
+    shared::<SIZE, Keyed<KeyedPoolAPI<Work<SIZE>, Crossbeam>>>(&mut group, "keyed4-crossbeam");
+    exclusive::<SIZE, Keyed<KeyedPoolAPI<Work<SIZE>, Spsc>>>(&mut group, "keyed4-spsc");
+    shared::<SIZE, DetachedAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, "detached1");
+    shared::<SIZE, DetachedAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, "detached4");
+    group.finish();
+}
+
+/// Round trips of a single command, every one of them is timed for the percentiles
+fn round_trips<const SIZE: usize, M: Manager<SIZE>>(
+    group: &mut BenchmarkGroup<WallTime>,
+    report: &mut Vec<String>,
+    name: &str,
+) {
+    let m = unsafe { M::start() };
+    let samples = RefCell::new(Vec::new());
+    group.bench_function(name, |b| {
+        b.iter_custom(|iters| {
+            let mut samples = samples.borrow_mut();
+            let start = Instant::now();
+            for _ in 0..iters {
+                let sent = Instant::now();
+                m.wait([m.submit(Work::new()).unwrap()]);
+                samples.push(sent.elapsed());
+            }
+            start.elapsed()
+        });
+    });
+    m.stop();
+
+    let mut samples = samples.into_inner();
+    // Filtered out
+    if samples.is_empty() {
+        return;
+    }
+    samples.sort_unstable();
+    let at = |q: f64| -> Duration {
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let index = ((samples.len() - 1) as f64 * q) as usize;
+        samples[index]
+    };
+    let percentiles = [at(0.5), at(0.9), at(0.99), at(0.999), at(1.0)];
+    println!(
+        "latency/{SIZE}B/{name}: p50 {:?} p90 {:?} p99 {:?} p99.9 {:?} max {:?}",
+        percentiles[0], percentiles[1], percentiles[2], percentiles[3], percentiles[4]
+    );
+    let nanos: Vec<_> = percentiles
+        .iter()
+        .map(|d| d.as_nanos().to_string())
+        .collect();
+    report.push(format!("{name},{}", nanos.join(",")));
+}
+
+fn latency<const SIZE: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("latency/{SIZE}B"));
+    let mut report = Vec::new();
+    let r = &mut report;
+    round_trips::<SIZE, SingleQueueChanAPI<Work<SIZE>, Std>>(&mut group, r, "single-std");
+    round_trips::<SIZE, SingleQueueChanAPI<Work<SIZE>, Crossbeam>>(
+        &mut group,
+        r,
+        "single-crossbeam",
+    );
+    #[cfg(feature = "flume")]
+    round_trips::<SIZE, SingleQueueChanAPI<Work<SIZE>, Flume>>(&mut group, r, "single-flume");
+    round_trips::<SIZE, SingleQueueChanAPI<Work<SIZE>, Spsc>>(&mut group, r, "single-spsc");
+    round_trips::<SIZE, PoolQueueAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, r, "pool1-crossbeam");
+    round_trips::<SIZE, PoolQueueAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, r, "pool4-crossbeam");
+    #[cfg(feature = "flume")]
+    round_trips::<SIZE, PoolQueueAPI<Work<SIZE>, 4, Flume>>(&mut group, r, "pool4-flume");
+    round_trips::<SIZE, OneShotChanAPI<Work<SIZE>, Std>>(&mut group, r, "oneshot-std");
+    round_trips::<SIZE, OneShotChanAPI<Work<SIZE>, Crossbeam>>(&mut group, r, "oneshot-crossbeam");
+    round_trips::<SIZE, OneShotChanAPI<Work<SIZE>, Spsc>>(&mut group, r, "oneshot-spsc");
+    round_trips::<SIZE, OneShotPoolAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, r, "oneshot-pool1");
+    round_trips::<SIZE, OneShotPoolAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, r, "oneshot-pool4");
+    #[cfg(feature = "flume")]
+    round_trips::<SIZE, OneShotPoolAPI<Work<SIZE>, 4, Flume>>(&mut group, r, "oneshot-pool4-flume");
+    round_trips::<SIZE, EnvelopePoolAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, r, "envelope-pool1");
+    round_trips::<SIZE, EnvelopePoolAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, r, "envelope-pool4");
+    round_trips::<SIZE, Keyed<KeyedPoolAPI<Work<SIZE>, Std>>>(&mut group, r, "keyed4-std");
+    round_trips::<SIZE, Keyed<KeyedPoolAPI<Work<SIZE>, Crossbeam>>>(
+        &mut group,
+        r,
+        "keyed4-crossbeam",
+    );
+    round_trips::<SIZE, Keyed<KeyedPoolAPI<Work<SIZE>, Spsc>>>(&mut group, r, "keyed4-spsc");
+    round_trips::<SIZE, DetachedAPI<Work<SIZE>, 1, Crossbeam>>(&mut group, r, "detached1");
+    round_trips::<SIZE, DetachedAPI<Work<SIZE>, 4, Crossbeam>>(&mut group, r, "detached4");
+    group.finish();
+
+    if report.is_empty() {
+        return;
+    }
+    let dir = format!("target/criterion/latency/{SIZE}B");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut csv = std::fs::File::create(format!("{dir}/percentiles.csv")).unwrap();
+    writeln!(csv, "manager,p50_ns,p90_ns,p99_ns,p99.9_ns,max_ns").unwrap();
+    for line in report {
+        writeln!(csv, "{line}").unwrap();
+    }
+}
+
+criterion_group!(
+    benches,
+    throughput<8>,
+    throughput<256>,
+    throughput<4096>,
+    latency<8>,
+    latency<256>,
+    latency<4096>,
+);
+criterion_main!(benches);
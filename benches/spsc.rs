@@ -0,0 +1,75 @@
+#![allow(clippy::unwrap_used, clippy::missing_panics_doc)]
+//! The 500k message workload of `queue::single_values` in the tests, on every channel a
+//! `SingleQueueAPI` can queue it's commands in
+//!
+//! Each iteration spawns and closes the manager, like the test does.
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use supera::channel::{Channel, Crossbeam, Spsc, Std};
+use supera::queue_single::SingleQueueChanAPI;
+use supera::{CommandRunner, ResultQueue};
+
+#[cfg(feature = "flume")]
+use supera::channel::Flume;
+
+const COUNT: usize = 500_000;
+
+#[derive(Debug, Clone, Copy)]
+enum Sub {
+    Of(u64, u64),
+    Stop,
+}
+
+impl supera::SimpleStop for Sub {
+    fn make_stop_command() -> Self {
+        Self::Stop
+    }
+}
+
+impl supera::Command for Sub {
+    type Result = u64;
+    fn execute(self) -> supera::ActionResult<u64> {
+        match self {
+            Self::Of(a, b) => supera::ActionResult::Normal(a - b),
+            Self::Stop => supera::ActionResult::Stop,
+        }
+    }
+}
+
+fn workload<Ch: Channel>() {
+    SingleQueueChanAPI::<Sub, Ch>::scope(|q| {
+        for _ in 0..COUNT {
+            q.send(black_box(Sub::Of(2, 1))).unwrap();
+        }
+        for _ in 0..COUNT {
+            black_box(q.results().recv().unwrap());
+        }
+    })
+    .unwrap();
+}
+
+fn channels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_values");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(COUNT as u64));
+    group.bench_function(BenchmarkId::from_parameter("std"), |b| {
+        b.iter(workload::<Std>);
+    });
+    group.bench_function(BenchmarkId::from_parameter("crossbeam"), |b| {
+        b.iter(workload::<Crossbeam>);
+    });
+    #[cfg(feature = "flume")]
+    group.bench_function(BenchmarkId::from_parameter("flume"), |b| {
+        b.iter(workload::<Flume>);
+    });
+    group.bench_function(BenchmarkId::from_parameter("spsc"), |b| {
+        b.iter(workload::<Spsc>);
+    });
+    group.bench_function(BenchmarkId::from_parameter("spsc-64"), |b| {
+        b.iter(workload::<Spsc<64>>);
+    });
+    group.finish();
+}
+
+criterion_group!(benches, channels);
+criterion_main!(benches);
@@ -0,0 +1,273 @@
+use crate::error::{CloseError, SendError};
+use crate::link::Link;
+use crate::oneshot_single::OneShotAPI;
+use crate::submit::{Closed, LinkSubmitter};
+use crate::{
+    ActionResult, CloseTimeout, Command, CommandRunner, RunnerConfig, SimpleStop, TimeoutReport,
+};
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
+use std::time::Duration;
+
+/// State that lives in a runner thread and is changed by the messages sent to it
+///
+/// The runner thread owns the actor while it runs, so it's handled without any locking. Messages
+/// are executed as commands of a [`OneShotAPI`], so they need the same bounds.
+pub trait Actor: Send + 'static {
+    type Msg: Send + Sync + 'static;
+    type Reply: Send + fmt::Debug + 'static;
+    fn handle(&mut self, msg: Self::Msg) -> Self::Reply;
+
+    /// Class of the message, for the limits set with [`RunnerConfig::limit`]
+    ///
+    /// A message over it's limits holds back the ones sent after it, so they're handled in order.
+    fn class(_msg: &Self::Msg) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Receiving end of an [`ActorRef::ask`]
+pub type Reply<A> = Link<<A as Actor>::Reply>;
+
+/// Failure to stop an actor, see [`CloseError`]
+pub type StopError<A> = CloseError<Mail<A>, <A as Actor>::Reply>;
+
+/// How an actor stopped within [`ActorHandle::stop_timeout`]
+pub type StopReport<A> =
+    TimeoutReport<<OneShotAPI<Mail<A>> as CloseTimeout>::Runner, <A as Actor>::Reply>;
+
+/// Where the actor waits for it's runner to start, and is given back once the runner stops
+type Home<A> = Arc<Mutex<Option<A>>>;
+type Mailbox<A> = mpsc::Sender<crate::oneshot::QueuedCommand<Mail<A>>>;
+
+/// The actor a runner thread owns, with the home it's given back to
+struct Slot<A> {
+    actor: A,
+    home: Home<A>,
+}
+
+thread_local! {
+    /// The [`Slot`] of the actor running on this thread, if it runs one
+    static SLOT: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// Moves the actor from it's home into the runner thread
+///
+/// # Panics
+/// Panics if the actor already left it's home, it only has one runner.
+fn settle<A: Actor>(home: &Home<A>) {
+    let actor = home.lock().unwrap_or_else(PoisonError::into_inner).take();
+    let actor = actor.expect("an actor is only started once");
+    let slot = Slot {
+        actor,
+        home: home.clone(),
+    };
+    SLOT.with_borrow_mut(|s| *s = Some(Box::new(slot)));
+}
+
+/// Gives the actor back to it's home, once the runner stops
+///
+/// # Panics
+/// Panics if the thread runs an actor of another type, which a runner never does.
+fn leave<A: Actor>() {
+    let Some(slot) = SLOT.with_borrow_mut(Option::take) else {
+        return;
+    };
+    let slot = slot
+        .downcast::<Slot<A>>()
+        .expect("the thread runs this actor");
+    *slot.home.lock().unwrap_or_else(PoisonError::into_inner) = Some(slot.actor);
+}
+
+/// A message to an actor, executed as a command of the [`OneShotAPI`] the actor runs on
+pub struct Mail<A: Actor>(Letter<A>);
+
+enum Letter<A: Actor> {
+    Msg(A::Msg),
+    Stop,
+}
+
+impl<A: Actor> Mail<A> {
+    /// Gives the message back, `None` for the stop command
+    #[must_use]
+    pub fn into_msg(self) -> Option<A::Msg> {
+        match self.0 {
+            Letter::Msg(msg) => Some(msg),
+            Letter::Stop => None,
+        }
+    }
+}
+
+impl<A: Actor> fmt::Debug for Mail<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Letter::Msg(..) => f.write_str("Mail(..)"),
+            Letter::Stop => f.write_str("Mail(Stop)"),
+        }
+    }
+}
+
+impl<A: Actor> SimpleStop for Mail<A> {
+    fn make_stop_command() -> Self {
+        Self(Letter::Stop)
+    }
+}
+
+impl<A: Actor> Command for Mail<A> {
+    type Result = A::Reply;
+    fn execute(self) -> ActionResult<A::Reply> {
+        let Letter::Msg(msg) = self.0 else {
+            leave::<A>();
+            return ActionResult::Stop;
+        };
+        SLOT.with_borrow_mut(|slot| {
+            // The actor only leaves once the runner stopped
+            let slot = slot.as_mut().and_then(|s| s.downcast_mut::<Slot<A>>());
+            let slot = slot.expect("the actor is running");
+            ActionResult::Normal(slot.actor.handle(msg))
+        })
+    }
+    fn class(&self) -> Option<&'static str> {
+        match &self.0 {
+            Letter::Msg(msg) => A::class(msg),
+            Letter::Stop => None,
+        }
+    }
+}
+
+/// Cloneable handle to send messages to an actor
+pub struct ActorRef<A>
+where
+    A: Actor,
+{
+    mailbox: LinkSubmitter<Mail<A>, Mailbox<A>>,
+}
+
+impl<A: Actor> Clone for ActorRef<A> {
+    fn clone(&self) -> Self {
+        Self {
+            mailbox: self.mailbox.clone(),
+        }
+    }
+}
+
+impl<A> ActorRef<A>
+where
+    A: Actor,
+{
+    /// Sends a message, discarding the actor's reply
+    ///
+    /// # Errors
+    /// Fails if the actor has stopped, or is stopping.
+    pub fn tell(&self, msg: A::Msg) -> Result<(), SendError<A::Msg>> {
+        self.mailbox
+            .send_with_callback(Mail(Letter::Msg(msg)), drop)
+            .map_err(unmail)
+    }
+
+    /// Sends a message, linking the actor's reply to the returned receiver
+    ///
+    /// # Errors
+    /// Fails if the actor has stopped, or is stopping.
+    pub fn ask(&self, msg: A::Msg) -> Result<Reply<A>, SendError<A::Msg>> {
+        self.mailbox.send(Mail(Letter::Msg(msg))).map_err(unmail)
+    }
+}
+
+fn unmail<A: Actor>(Closed(mail): Closed<Mail<A>>) -> SendError<A::Msg> {
+    match mail.into_msg() {
+        Some(msg) => SendError(msg),
+        None => unreachable!("stop is only sent by the actor's handle"),
+    }
+}
+
+/// Takes the actor's final state, once it's runner stopped
+fn take<A>(home: &Home<A>) -> Option<A> {
+    home.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+/// Owner of an actor's runner thread
+pub struct ActorHandle<A>
+where
+    A: Actor,
+{
+    actor_ref: ActorRef<A>,
+    manager: OneShotAPI<Mail<A>>,
+    home: Home<A>,
+}
+
+impl<A> ActorHandle<A>
+where
+    A: Actor,
+{
+    /// # Safety
+    /// Since this starts another thread, it's only safe to call this if [`ActorHandle::stop`] is
+    /// called.
+    ///
+    /// [`ActorHandle::scope`] always stops the actor.
+    #[must_use]
+    pub unsafe fn spawn(actor: A) -> Self {
+        unsafe { Self::spawn_with_config(actor, &RunnerConfig::default()) }
+    }
+
+    /// # Safety
+    /// See [`ActorHandle::spawn`]
+    #[must_use]
+    pub unsafe fn spawn_with_config(actor: A, config: &RunnerConfig) -> Self {
+        let home = Arc::new(Mutex::new(Some(actor)));
+        let start = home.clone();
+        let config = config.clone().in_order().on_start(move || settle(&start));
+        let manager = unsafe { OneShotAPI::with_config(&config) };
+        let actor_ref = ActorRef {
+            mailbox: manager.submitter(),
+        };
+        Self {
+            actor_ref,
+            manager,
+            home,
+        }
+    }
+
+    #[must_use]
+    pub fn actor_ref(&self) -> ActorRef<A> {
+        self.actor_ref.clone()
+    }
+
+    /// Stops the actor after it handles every message sent before this call, returning it's
+    /// final state.
+    ///
+    /// # Errors
+    /// Fails like closing a [`OneShotAPI`], e.g. if the actor panicked.
+    // The state is only missing if the runner panicked, which closing reports
+    #[allow(clippy::missing_panics_doc)]
+    pub fn stop(self) -> Result<A, StopError<A>> {
+        self.manager.close()?;
+        Ok(take(&self.home).expect("the actor stopped cleanly"))
+    }
+
+    /// Same as [`ActorHandle::stop`], but only waits `timeout` for the actor to stop
+    ///
+    /// # Errors
+    /// Gives the report back if the actor didn't stop cleanly in time, see [`CloseTimeout`].
+    // The state is only missing if the runner panicked, which the report tells
+    #[allow(clippy::missing_panics_doc)]
+    pub fn stop_timeout(self, timeout: Duration) -> Result<A, StopReport<A>> {
+        let report = self.manager.close_timeout(timeout);
+        if !report.is_clean() {
+            return Err(report);
+        }
+        Ok(take(&self.home).expect("the actor stopped cleanly"))
+    }
+
+    /// No need to remember to stop the actor if you use scope
+    ///
+    /// # Errors
+    /// See [`ActorHandle::stop`]
+    pub fn scope(actor: A, f: impl FnOnce(&ActorRef<A>)) -> Result<A, StopError<A>> {
+        let handle = unsafe { Self::spawn(actor) };
+        f(&handle.actor_ref);
+        handle.stop()
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{ChanRecv, ChanSend};
+use crossbeam_channel as mpmc;
+use std::sync::mpsc;
+
+mod spsc;
+pub use spsc::{Spsc, SpscReceiver, SpscSender};
+
+/// Creates the command queues of a manager, so it's runners can be fed by any channel that
+/// implements [`ChanSend`] and [`ChanRecv`]
+pub trait Channel {
+    /// Only needs to be [`Clone`] for the manager to hand out submitters
+    type Sender<T: Send + 'static>: ChanSend<T> + Send + 'static;
+    type Receiver<T: Send + 'static>: ChanRecv<T> + Send + 'static;
+    fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>);
+}
+
+/// A [`Channel`] whose receiver can be shared by the many runners of a pool
+pub trait SharedChannel: Channel {
+    /// Another receiver of the same queue, each item is received by only one of them
+    fn share<T: Send + 'static>(recv: &Self::Receiver<T>) -> Self::Receiver<T>;
+}
+
+/// [`std::sync::mpsc`]'s channel, can only be used by single runner managers
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Std;
+
+impl Channel for Std {
+    type Sender<T: Send + 'static> = mpsc::Sender<T>;
+    type Receiver<T: Send + 'static> = mpsc::Receiver<T>;
+    fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+        mpsc::channel()
+    }
+}
+
+/// [`crossbeam_channel`]'s unbounded channel
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Crossbeam;
+
+impl Channel for Crossbeam {
+    type Sender<T: Send + 'static> = mpmc::Sender<T>;
+    type Receiver<T: Send + 'static> = mpmc::Receiver<T>;
+    fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+        mpmc::unbounded()
+    }
+}
+
+impl SharedChannel for Crossbeam {
+    fn share<T: Send + 'static>(recv: &Self::Receiver<T>) -> Self::Receiver<T> {
+        recv.clone()
+    }
+}
+
+/// [`flume`]'s unbounded channel
+#[cfg(feature = "flume")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Flume;
+
+#[cfg(feature = "flume")]
+impl Channel for Flume {
+    type Sender<T: Send + 'static> = flume::Sender<T>;
+    type Receiver<T: Send + 'static> = flume::Receiver<T>;
+    fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+        flume::unbounded()
+    }
+}
+
+#[cfg(feature = "flume")]
+impl SharedChannel for Flume {
+    fn share<T: Send + 'static>(recv: &Self::Receiver<T>) -> Self::Receiver<T> {
+        recv.clone()
+    }
+}
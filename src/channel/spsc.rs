@@ -0,0 +1,314 @@
+use super::Channel;
+use crate::error::SendError;
+use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering, fence};
+use crate::sync::thread::{self, Thread};
+use crate::sync::{Mutex, UnsafeCell, hint};
+use crate::{ChanRecv, ChanSend};
+use crossbeam_channel as mpmc;
+use std::cell::Cell;
+use std::mem::MaybeUninit;
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How many times a side checks the ring again before yielding it's thread
+#[cfg(not(loom))]
+const SPINS: u32 = 64;
+/// How many times it yields before parking
+#[cfg(not(loom))]
+const YIELDS: u32 = 16;
+/// Spinning only adds states to loom's models, so they only do a couple rounds of each
+#[cfg(loom)]
+const SPINS: u32 = 2;
+#[cfg(loom)]
+const YIELDS: u32 = 2;
+
+/// Keeps the producer's and consumer's indices in different cache lines
+#[repr(align(128))]
+struct Padded<T>(T);
+
+/// A side of the ring that's parked, waiting for the other
+#[derive(Default)]
+struct Parked {
+    waiting: AtomicBool,
+    thread: Mutex<Option<Thread>>,
+}
+
+impl Parked {
+    /// Parks until `ready` or the deadline passes, returns if `ready`
+    fn wait(&self, deadline: Option<Instant>, ready: impl Fn() -> bool) -> bool {
+        for i in 0..SPINS + YIELDS {
+            if ready() {
+                return true;
+            }
+            if i < SPINS {
+                hint::spin_loop();
+            } else {
+                thread::yield_now();
+            }
+        }
+        *self.thread.lock().unwrap_or_else(PoisonError::into_inner) = Some(thread::current());
+        // The flag is only ever swapped, loom can misorder a plain store to it with the other
+        // side's swap
+        loop {
+            self.waiting.swap(true, Ordering::SeqCst);
+            // Checked after announcing the wait, so the other side either sees it or it's
+            // change is seen here
+            fence(Ordering::SeqCst);
+            if ready() {
+                self.waiting.swap(false, Ordering::SeqCst);
+                return true;
+            }
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                        self.waiting.swap(false, Ordering::SeqCst);
+                        return false;
+                    };
+                    thread::park_timeout(left);
+                }
+            }
+        }
+    }
+
+    fn wake(&self) {
+        // Pairs with the fence in `wait`
+        fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::Relaxed)
+            && self.waiting.swap(false, Ordering::SeqCst)
+            && let Some(t) = &*self.thread.lock().unwrap_or_else(PoisonError::into_inner)
+        {
+            t.unpark();
+        }
+    }
+}
+
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// Next slot to read, only written by the consumer
+    head: Padded<AtomicUsize>,
+    /// Next slot to write, only written by the producer
+    tail: Padded<AtomicUsize>,
+    /// Either side was dropped
+    closed: AtomicBool,
+    consumer: Parked,
+    producer: Parked,
+}
+
+// Each slot is only accessed by one side at a time, as given by `head` and `tail`
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+    /// # Panics
+    /// Panics if `cap` is zero.
+    fn with_capacity(cap: usize) -> Self {
+        assert!(cap > 0, "a ring needs at least one slot");
+        Self {
+            slots: (0..cap)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            head: Padded(AtomicUsize::new(0)),
+            tail: Padded(AtomicUsize::new(0)),
+            closed: AtomicBool::new(false),
+            consumer: Parked::default(),
+            producer: Parked::default(),
+        }
+    }
+
+    fn slot(&self, index: usize) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.slots[index % self.slots.len()]
+    }
+
+    fn is_full(&self) -> bool {
+        let head = self.head.0.load(Ordering::Acquire);
+        self.tail.0.load(Ordering::Relaxed) - head == self.slots.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.0.load(Ordering::Relaxed) == self.tail.0.load(Ordering::Acquire)
+    }
+
+    /// `head` is the producer's last known value of it, so it only reads the consumer's index
+    /// again once the ring seems full
+    ///
+    /// # Safety
+    /// Must only be called by the producer.
+    ///
+    /// # Errors
+    /// Gives `t` back if the ring is full or the consumer is gone.
+    unsafe fn push(&self, head: &Cell<usize>, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(mpmc::TrySendError::Disconnected(t));
+        }
+        let tail = self.tail.0.load(Ordering::Relaxed);
+        if tail - head.get() == self.slots.len() {
+            head.set(self.head.0.load(Ordering::Acquire));
+            if tail - head.get() == self.slots.len() {
+                return Err(mpmc::TrySendError::Full(t));
+            }
+        }
+        self.slot(tail).with_mut(|slot| unsafe { (*slot).write(t) });
+        self.tail.0.store(tail + 1, Ordering::Release);
+        self.consumer.wake();
+        Ok(())
+    }
+
+    /// `tail` is the consumer's last known value of it, so it only reads the producer's index
+    /// again once the ring seems empty
+    ///
+    /// # Safety
+    /// Must only be called by the consumer.
+    ///
+    /// # Errors
+    /// Fails if the ring is empty, or it's empty and the producer is gone.
+    unsafe fn pop(&self, tail: &Cell<usize>) -> Result<T, mpmc::TryRecvError> {
+        let head = self.head.0.load(Ordering::Relaxed);
+        if head == tail.get() {
+            // Read before checking for items, so the last items sent are still received
+            let closed = self.closed.load(Ordering::Acquire);
+            tail.set(self.tail.0.load(Ordering::Acquire));
+            if head == tail.get() {
+                return Err(if closed {
+                    mpmc::TryRecvError::Disconnected
+                } else {
+                    mpmc::TryRecvError::Empty
+                });
+            }
+        }
+        let t = self
+            .slot(head)
+            .with_mut(|slot| unsafe { (*slot).assume_init_read() });
+        self.head.0.store(head + 1, Ordering::Release);
+        self.producer.wake();
+        Ok(t)
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.consumer.wake();
+        self.producer.wake();
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let tail = self.tail.0.load(Ordering::Relaxed);
+        for index in self.head.0.load(Ordering::Relaxed)..tail {
+            self.slot(index)
+                .with_mut(|slot| unsafe { (*slot).assume_init_drop() });
+        }
+    }
+}
+
+/// Sending half of a [`Spsc`] channel
+///
+/// It can't be cloned or shared, since only one thread may send at a time.
+pub struct SpscSender<T> {
+    ring: Arc<Ring<T>>,
+    head: Cell<usize>,
+}
+
+/// Receiving half of a [`Spsc`] channel
+pub struct SpscReceiver<T> {
+    ring: Arc<Ring<T>>,
+    tail: Cell<usize>,
+}
+
+impl<T> Drop for SpscSender<T> {
+    fn drop(&mut self) {
+        self.ring.close();
+    }
+}
+
+impl<T> Drop for SpscReceiver<T> {
+    fn drop(&mut self) {
+        self.ring.close();
+    }
+}
+
+impl<T> ChanSend<T> for SpscSender<T> {
+    type Err = SendError<T>;
+    fn send_t(&self, mut t: T) -> Result<(), Self::Err> {
+        loop {
+            match unsafe { self.ring.push(&self.head, t) } {
+                Ok(()) => return Ok(()),
+                Err(mpmc::TrySendError::Disconnected(t)) => return Err(SendError(t)),
+                Err(mpmc::TrySendError::Full(back)) => t = back,
+            }
+            let ring = &self.ring;
+            ring.producer.wait(None, || {
+                !ring.is_full() || ring.closed.load(Ordering::SeqCst)
+            });
+        }
+    }
+    fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        unsafe { self.ring.push(&self.head, t) }
+    }
+}
+
+impl<T> SpscReceiver<T> {
+    /// # Errors
+    /// Fails if nothing is received before `deadline`, or the sender is gone and every item was
+    /// received.
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<T, mpmc::RecvTimeoutError> {
+        loop {
+            match unsafe { self.ring.pop(&self.tail) } {
+                Ok(t) => return Ok(t),
+                Err(mpmc::TryRecvError::Disconnected) => {
+                    return Err(mpmc::RecvTimeoutError::Disconnected);
+                }
+                Err(mpmc::TryRecvError::Empty) => {}
+            }
+            let ring = &self.ring;
+            let ready = ring.consumer.wait(deadline, || {
+                !ring.is_empty() || ring.closed.load(Ordering::SeqCst)
+            });
+            if !ready {
+                return Err(mpmc::RecvTimeoutError::Timeout);
+            }
+        }
+    }
+}
+
+impl<T> ChanRecv<T> for SpscReceiver<T> {
+    type Err = mpmc::RecvError;
+    fn recv_t(&self) -> Result<T, Self::Err> {
+        self.recv_until(None).map_err(|_| mpmc::RecvError)
+    }
+    fn try_recv_t(&self) -> Result<T, mpmc::TryRecvError> {
+        unsafe { self.ring.pop(&self.tail) }
+    }
+    fn recv_timeout_t(&self, timeout: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+        // A deadline too far to represent is never reached
+        self.recv_until(Instant::now().checked_add(timeout))
+    }
+}
+
+/// Bounded single producer, single consumer ring buffer of `CAP` slots
+///
+/// Both sides spin for a bit before parking while the ring is empty or full, which avoids the
+/// cost of waking up a thread when commands are sent at a high rate. Since it's sender can't be
+/// cloned, managers using it don't hand out submitters.
+///
+/// # Panics
+/// Creating a channel panics if `CAP` is zero.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Spsc<const CAP: usize = 1024>;
+
+impl<const CAP: usize> Channel for Spsc<CAP> {
+    type Sender<T: Send + 'static> = SpscSender<T>;
+    type Receiver<T: Send + 'static> = SpscReceiver<T>;
+    fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+        let ring = Arc::new(Ring::with_capacity(CAP));
+        let send = SpscSender {
+            ring: ring.clone(),
+            head: Cell::new(0),
+        };
+        let recv = SpscReceiver {
+            ring,
+            tail: Cell::new(0),
+        };
+        (send, recv)
+    }
+}
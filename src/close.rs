@@ -0,0 +1,256 @@
+use crate::error::{CloseError, EventLoopError};
+use crate::sync::thread::{self, JoinHandle};
+use crate::{ChanSend, CloseOutcome, CommandRunner, SimpleCloser, SimpleStop, StopRunner, Stopped};
+use crossbeam_channel as mpmc;
+use std::any::Any;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+type Exit<T, Rst> = Result<T, EventLoopError<Rst>>;
+/// A runner's index, and how it stopped
+type Joined<T, Rst> = (usize, Exit<T, Rst>);
+
+/// How often a timed close checks on the runners, and a submitter tries a full channel again
+pub(crate) const POLL: Duration = Duration::from_millis(1);
+
+/// When a timed close gives up, `None` if `timeout` is too long for that to ever happen
+pub(crate) fn deadline(timeout: Duration) -> Option<Instant> {
+    Instant::now().checked_add(timeout)
+}
+
+/// How long to sleep before checking again, `None` once `deadline` passed
+fn nap(deadline: Option<Instant>) -> Option<Duration> {
+    let Some(deadline) = deadline else {
+        return Some(POLL);
+    };
+    let left = deadline.checked_duration_since(Instant::now())?;
+    (!left.is_zero()).then(|| POLL.min(left))
+}
+
+/// How each runner of a pool stopped, in the order they were spawned
+///
+/// Closing a pool always stops and joins every runner, so one that stopped by itself doesn't
+/// hide what happened to the others.
+#[must_use]
+pub struct CloseReport<Cmd, T, Rst> {
+    workers: Vec<Exit<T, Rst>>,
+    d: PhantomData<Cmd>,
+}
+
+impl<Cmd, T, Rst> CloseReport<Cmd, T, Rst> {
+    /// Joins every runner, once each was sent a stop command
+    pub(crate) fn join(runners: impl IntoIterator<Item = JoinHandle<Exit<T, Rst>>>) -> Self {
+        Self {
+            workers: runners
+                .into_iter()
+                .map(|r| r.join().map_err(EventLoopError::Panic)?)
+                .collect(),
+            d: PhantomData,
+        }
+    }
+
+    pub fn workers(&self) -> &[Exit<T, Rst>] {
+        &self.workers
+    }
+
+    #[must_use]
+    pub fn into_workers(self) -> Vec<Exit<T, Rst>> {
+        self.workers
+    }
+
+    /// Runners that stopped when asked to, along with their index
+    pub fn clean(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.workers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, w)| w.as_ref().ok().map(|t| (i, t)))
+    }
+
+    /// Runners that stopped by themselves, other than by panicking
+    pub fn failed(&self) -> impl Iterator<Item = (usize, &EventLoopError<Rst>)> {
+        self.workers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, w)| match w {
+                Err(EventLoopError::Panic(..)) | Ok(..) => None,
+                Err(e) => Some((i, e)),
+            })
+    }
+
+    /// Payloads of the runners that panicked
+    pub fn panicked(&self) -> impl Iterator<Item = (usize, &(dyn Any + Send))> {
+        self.workers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, w)| match w {
+                Err(EventLoopError::Panic(payload)) => Some((i, &**payload)),
+                _ => None,
+            })
+    }
+
+    /// Every runner stopped when asked to
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.workers.iter().all(Result::is_ok)
+    }
+
+    /// # Errors
+    /// Fails with the first runner that didn't stop when asked to.
+    pub fn into_result(self) -> Result<Vec<T>, CloseError<Cmd, Rst>> {
+        self.workers
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+}
+
+impl<Cmd, T, Rst> IntoIterator for CloseReport<Cmd, T, Rst> {
+    type Item = Exit<T, Rst>;
+    type IntoIter = std::vec::IntoIter<Exit<T, Rst>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.workers.into_iter()
+    }
+}
+
+impl<Cmd, T, Rst> CloseOutcome for CloseReport<Cmd, T, Rst>
+where
+    T: Stopped<Rst>,
+{
+    type Cmd = Cmd;
+    type Rst = Rst;
+    fn into_errors(self) -> Vec<CloseError<Cmd, Rst>> {
+        self.workers
+            .into_iter()
+            .flat_map(|w| match w {
+                Ok(runner) => runner.failures(),
+                Err(e) => vec![e],
+            })
+            .map(Into::into)
+            .collect()
+    }
+}
+
+/// A manager that can give up on it's runners when closing it takes too long
+pub trait CloseTimeout: CommandRunner {
+    /// What a runner gives back once it's joined
+    type Runner;
+    /// Type of the results the runners deliver
+    type Rst;
+
+    /// Same as [`CommandRunner::close_with`], but only waits `timeout` for the stop commands to
+    /// be sent and the runners to stop
+    ///
+    /// Runners that are still going by then, e.g. because a command hangs, are handed back as
+    /// [`Unfinished`]. A `timeout` too long to ever be reached waits for every runner.
+    fn close_timeout_with(
+        self,
+        s: impl StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst>;
+
+    fn close_timeout(self, timeout: Duration) -> TimeoutReport<Self::Runner, Self::Rst>
+    where
+        Self::Cmd: SimpleStop,
+        Self: Sized,
+    {
+        self.close_timeout_with(SimpleCloser, timeout)
+    }
+}
+
+/// A runner that was still going once a timed close gave up on it
+///
+/// Dropping it detaches the runner's thread, which then stops by itself once it's done with the
+/// stop command, if it was sent.
+pub struct Unfinished<T, Rst> {
+    index: usize,
+    thread: JoinHandle<Exit<T, Rst>>,
+}
+
+impl<T, Rst> Unfinished<T, Rst> {
+    /// Index of the runner in it's manager
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        thread::is_finished(&self.thread)
+    }
+
+    /// Blocks until the runner stops
+    ///
+    /// # Errors
+    /// Fails if the runner stopped by itself or panicked.
+    pub fn join(self) -> Exit<T, Rst> {
+        self.thread.join().map_err(EventLoopError::Panic)?
+    }
+}
+
+/// How the runners of a manager stopped within a timed close
+#[must_use]
+pub struct TimeoutReport<T, Rst> {
+    /// Runners that stopped in time, along with their index
+    pub stopped: Vec<Joined<T, Rst>>,
+    /// Runners that were still going at the deadline
+    pub unfinished: Vec<Unfinished<T, Rst>>,
+}
+
+impl<T, Rst> TimeoutReport<T, Rst> {
+    /// Joins each runner that stops before `deadline`, or every runner without one
+    pub(crate) fn join_until(
+        runners: impl IntoIterator<Item = JoinHandle<Exit<T, Rst>>>,
+        deadline: Option<Instant>,
+    ) -> Self {
+        let mut unfinished: Vec<_> = runners
+            .into_iter()
+            .enumerate()
+            .map(|(index, thread)| Unfinished { index, thread })
+            .collect();
+        let mut stopped = Vec::new();
+        loop {
+            let (done, left): (Vec<_>, Vec<_>) =
+                unfinished.into_iter().partition(Unfinished::is_finished);
+            stopped.extend(done.into_iter().map(|u| (u.index, u.join())));
+            unfinished = left;
+            if unfinished.is_empty() {
+                break;
+            }
+            let Some(nap) = nap(deadline) else { break };
+            std::thread::sleep(nap);
+        }
+        stopped.sort_by_key(|(i, _)| *i);
+        Self {
+            stopped,
+            unfinished,
+        }
+    }
+
+    /// Every runner stopped in time, when asked to
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.unfinished.is_empty() && self.stopped.iter().all(|(_, r)| r.is_ok())
+    }
+}
+
+/// Sends `t` unless the channel stays full until `deadline`, or forever without one
+///
+/// # Errors
+/// Gives `t` back if it couldn't be sent in time, or the channel is disconnected.
+pub(crate) fn send_until<T>(
+    chan: &impl ChanSend<T>,
+    mut t: T,
+    deadline: Option<Instant>,
+) -> Result<(), T> {
+    loop {
+        match chan.try_send_t(t) {
+            Ok(()) => return Ok(()),
+            Err(mpmc::TrySendError::Disconnected(back)) => return Err(back),
+            Err(mpmc::TrySendError::Full(back)) => t = back,
+        }
+        let Some(nap) = nap(deadline) else {
+            return Err(t);
+        };
+        std::thread::sleep(nap);
+    }
+}
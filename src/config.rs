@@ -0,0 +1,292 @@
+use crate::limit::{Classes, Dispatch, Limit};
+use crate::sync::thread::{self, JoinHandle};
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fmt, io};
+
+/// Handler for results no one will receive, see [`RunnerConfig::on_abandoned`]
+#[derive(Clone)]
+struct Handler(Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>);
+
+impl fmt::Debug for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Handler(..)")
+    }
+}
+
+/// Called on every runner thread before it receives it's first command, see
+/// [`RunnerConfig::on_start`]
+#[derive(Clone)]
+struct Hook(Arc<dyn Fn() + Send + Sync>);
+
+impl fmt::Debug for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hook(..)")
+    }
+}
+
+/// What every runner of a manager did so far, shared by them
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    pub(crate) received: AtomicU64,
+    pub(crate) executed: AtomicU64,
+    pub(crate) abandoned: AtomicU64,
+}
+
+/// Counts what a runner does into it's manager's [`Counters`], if they're kept
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tally(Option<Arc<Counters>>);
+
+impl Tally {
+    fn add(&self, counter: impl FnOnce(&Counters) -> &AtomicU64) {
+        if let Some(counters) = &self.0 {
+            counter(counters).fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// A command was taken off the queue, stop commands included
+    pub(crate) fn received(&self) {
+        self.add(|c| &c.received);
+    }
+
+    pub(crate) fn executed(&self) {
+        self.add(|c| &c.executed);
+    }
+}
+
+/// Results a runner couldn't deliver
+#[derive(Debug, Default)]
+pub(crate) struct Abandoned {
+    count: u64,
+    handler: Option<Handler>,
+    tally: Tally,
+}
+
+impl Abandoned {
+    /// Counts `rst` and hands it to the handler, if there's one
+    pub(crate) fn give<T: Send + 'static>(&mut self, rst: T) {
+        self.count += 1;
+        self.tally.add(|c| &c.abandoned);
+        if let Some(h) = &self.handler {
+            (h.0)(Box::new(rst));
+        }
+    }
+
+    /// Counts a callback that panicked with it's result, handing the payload to the handler
+    pub(crate) fn panicked(&mut self, payload: Box<dyn Any + Send>) {
+        self.count += 1;
+        self.tally.add(|c| &c.abandoned);
+        if let Some(h) = &self.handler {
+            (h.0)(payload);
+        }
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// Configuration of the threads a manager spawns for it's runners
+///
+/// Every runner thread is named `{prefix}-{index}`, the prefix defaults to a name specific to each
+/// manager, such as `supera-pool`.
+#[derive(Debug, Clone, Default)]
+pub struct RunnerConfig {
+    name: Option<String>,
+    stack_size: Option<usize>,
+    #[cfg(target_os = "linux")]
+    affinity: Option<Vec<usize>>,
+    #[cfg(target_os = "linux")]
+    niceness: Option<i32>,
+    abandoned: Option<Handler>,
+    start: Option<Hook>,
+    limits: Classes,
+    in_order: bool,
+    counters: Tally,
+}
+
+impl RunnerConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix of the runner threads' names
+    #[must_use]
+    pub fn name(mut self, prefix: impl Into<String>) -> Self {
+        self.name = Some(prefix.into());
+        self
+    }
+
+    /// Stack size, in bytes, of each runner thread
+    #[must_use]
+    pub fn stack_size(mut self, bytes: usize) -> Self {
+        self.stack_size = Some(bytes);
+        self
+    }
+
+    /// Pin every runner thread to the given set of CPUs
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn affinity(mut self, cpus: impl IntoIterator<Item = usize>) -> Self {
+        self.affinity = Some(cpus.into_iter().collect());
+        self
+    }
+
+    /// Set the niceness of every runner thread
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn niceness(mut self, nice: i32) -> Self {
+        self.niceness = Some(nice);
+        self
+    }
+
+    /// Called on a runner's thread with every result it couldn't deliver, because it's link or
+    /// the manager's result queue was dropped
+    ///
+    /// The runner keeps going either way. The result can be downcast to what the runner
+    /// delivers, the command's result or an [`Envelope`](crate::queue_envelope::Envelope) of it.
+    /// A callback of a linked manager that panics loses it's result, and it's the panic's payload
+    /// that's handed over instead.
+    #[must_use]
+    pub fn on_abandoned(mut self, f: impl Fn(Box<dyn Any + Send>) + Send + Sync + 'static) -> Self {
+        self.abandoned = Some(Handler(Arc::new(f)));
+        self
+    }
+
+    /// Limits the commands whose [`Command::class`](crate::Command::class) is `class`
+    ///
+    /// A runner puts off the commands that are over their class's limits, and keeps executing
+    /// those of other classes meanwhile. Every command it put off is still executed before it
+    /// stops, but a manager's results may then be out of order. The runners of a
+    /// [`KeyedPoolAPI`](crate::queue_keyed::KeyedPoolAPI) and of an
+    /// [actor](crate::actor::ActorHandle) wait for the command's permit instead, so they keep
+    /// the order commands were sent in. The limits are shared by every manager spawned with
+    /// this config, or a clone of it.
+    #[must_use]
+    pub fn limit(mut self, class: &'static str, limit: Limit) -> Self {
+        self.limits.insert(class, limit);
+        self
+    }
+
+    /// The runners spawned with this config execute their commands in the order they're received,
+    /// waiting for a limited command's permit instead of putting it off
+    pub(crate) fn in_order(mut self) -> Self {
+        self.in_order = true;
+        self
+    }
+
+    /// Hands a runner its commands under the limits of this config
+    pub(crate) fn dispatch<T>(&self) -> Dispatch<T> {
+        Dispatch::new(self.limits.clone(), self.in_order)
+    }
+
+    /// Calls `f` on every runner thread spawned with this config, before it's first command
+    pub(crate) fn on_start(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.start = Some(Hook(Arc::new(f)));
+        self
+    }
+
+    /// Counts what the runners spawned with this config do into `counters`
+    pub(crate) fn counted(mut self, counters: Arc<Counters>) -> Self {
+        self.counters = Tally(Some(counters));
+        self
+    }
+
+    pub(crate) fn tally(&self) -> Tally {
+        self.counters.clone()
+    }
+
+    /// Tracks the results of one runner
+    pub(crate) fn abandoned(&self) -> Abandoned {
+        Abandoned {
+            count: 0,
+            handler: self.abandoned.clone(),
+            tally: self.tally(),
+        }
+    }
+
+    pub(crate) fn thread_name(&self, default_prefix: &str, index: usize) -> String {
+        let prefix = self.name.as_deref().unwrap_or(default_prefix);
+        format!("{prefix}-{index}")
+    }
+
+    /// Spawns the `index`th runner thread of a manager.
+    ///
+    /// The scheduling options are applied from inside the new thread, and the start hook called,
+    /// before `f` is called.
+    ///
+    /// # Panics
+    /// Like [`std::thread::spawn`], panics if the OS fails to create the thread.
+    pub(crate) fn spawn<F, T, E>(
+        &self,
+        default_prefix: &str,
+        index: usize,
+        f: F,
+    ) -> JoinHandle<Result<T, E>>
+    where
+        F: FnOnce() -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<io::Error> + Send + 'static,
+    {
+        let mut builder = thread::Builder::new().name(self.thread_name(default_prefix, index));
+        if let Some(size) = self.stack_size {
+            builder = builder.stack_size(size);
+        }
+        let setup = self.clone();
+        builder
+            .spawn(move || {
+                setup.apply()?;
+                if let Some(start) = &setup.start {
+                    (start.0)();
+                }
+                f()
+            })
+            .expect("failed to spawn runner thread")
+    }
+
+    /// Applies the scheduling options to the current thread
+    ///
+    /// # Errors
+    /// Returns the OS error if the affinity or niceness could not be set.
+    #[cfg(target_os = "linux")]
+    fn apply(&self) -> io::Result<()> {
+        if let Some(cpus) = &self.affinity {
+            // SAFETY: `cpu_set_t` is a plain bitmask, all zeros is the empty set
+            let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+            for &cpu in cpus {
+                if cpu >= libc::CPU_SETSIZE as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("CPU {cpu} is out of range"),
+                    ));
+                }
+                // SAFETY: `cpu` was checked to be in the set's range
+                unsafe { libc::CPU_SET(cpu, &mut set) };
+            }
+            // SAFETY: `set` is a valid `cpu_set_t` and pid 0 refers to the calling thread
+            let r =
+                unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &raw const set) };
+            if r != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if let Some(nice) = self.niceness {
+            // SAFETY: gettid has no preconditions
+            let tid = unsafe { libc::gettid() };
+            let tid = libc::id_t::try_from(tid).map_err(io::Error::other)?;
+            // SAFETY: on Linux, `PRIO_PROCESS` with a thread id only affects that thread
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    fn apply(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
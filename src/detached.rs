@@ -0,0 +1,177 @@
+use crate::channel::{Channel, Crossbeam, SharedChannel};
+use crate::close::{self, send_until};
+use crate::error::{EventLoopError, SendError};
+use crate::queue::QueueRunner;
+use crate::runtime::Runners;
+use crate::submit::{Gate, Submitter};
+use crate::sync::thread::JoinHandle;
+use crate::{
+    ChanSend, CloseReport, CloseTimeout, CmdRst, Command, CommandRunner, RunnerConfig,
+    TimeoutReport,
+};
+use crossbeam_channel as mpmc;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+type DetachedRunner<Cmd, Ch> = QueueRunner<Cmd, <Ch as Channel>::Receiver<Cmd>, Sink<CmdRst<Cmd>>>;
+type Worker<Cmd, Ch> = JoinHandle<Result<DetachedRunner<Cmd, Ch>, EventLoopError<CmdRst<Cmd>>>>;
+
+/// Where a [`DetachedAPI`]'s runners put the results of their commands
+pub trait ResultSink<Rst>: Send + Sync + 'static {
+    fn accept(&self, result: Rst);
+}
+
+/// Drops every result
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Discard;
+
+impl<Rst> ResultSink<Rst> for Discard {
+    fn accept(&self, _: Rst) {}
+}
+
+/// Hands only the errors of fallible commands to the wrapped function
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ErrorSink<F>(pub F);
+
+impl<T, E, F> ResultSink<Result<T, E>> for ErrorSink<F>
+where
+    F: Fn(E) + Send + Sync + 'static,
+{
+    fn accept(&self, result: Result<T, E>) {
+        if let Err(e) = result {
+            (self.0)(e);
+        }
+    }
+}
+
+/// Result "channel" of a detached runner, it can't fail since nothing waits on the other side
+pub struct Sink<Rst>(Arc<dyn ResultSink<Rst>>);
+
+impl<T: 'static> ChanSend<T> for Sink<T> {
+    type Err = Infallible;
+    fn send_t(&self, t: T) -> Result<(), Self::Err> {
+        self.0.accept(t);
+        Ok(())
+    }
+    fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        self.0.accept(t);
+        Ok(())
+    }
+}
+
+/// API of [`QueueRunner`] for managing runners whose results are not sent back
+///
+/// Results are handed to a [`ResultSink`] on the runner's thread instead, by default they are
+/// [discarded](Discard). Use `N = 1` for a single runner.
+///
+/// Commands are queued in a `Ch` [channel](crate::channel), [`crossbeam_channel`]'s by default.
+/// It must be a [`SharedChannel`] whatever `N` is, so [`Std`](crate::channel::Std) and
+/// [`Spsc`](crate::channel::Spsc) can't be used, even for a single runner.
+pub struct DetachedAPI<Cmd, const N: usize, Ch = Crossbeam>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    send_cmd: Ch::Sender<Cmd>,
+    runners: [Worker<Cmd, Ch>; N],
+    gate: Arc<Gate>,
+}
+
+impl<Cmd, const N: usize, Ch> DetachedAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    CmdRst<Cmd>: 'static,
+    Ch: SharedChannel,
+{
+    /// # Safety
+    /// See [`CommandRunner::new`]
+    #[must_use]
+    pub unsafe fn with_sink(sink: impl ResultSink<CmdRst<Cmd>>, config: &RunnerConfig) -> Self {
+        let (tx_cmd, rx_cmd) = Ch::channel();
+        let sink: Arc<dyn ResultSink<CmdRst<Cmd>>> = Arc::new(sink);
+        let runners = std::array::from_fn(|i| {
+            QueueRunner::spawn(
+                config,
+                "supera-detach",
+                i,
+                Ch::share(&rx_cmd),
+                Sink(sink.clone()),
+            )
+        });
+        Self {
+            send_cmd: tx_cmd,
+            runners,
+            gate: Arc::default(),
+        }
+    }
+
+    /// Handle that can send commands from other threads, until the manager is closed
+    #[must_use]
+    pub fn submitter(&self) -> Submitter<Cmd, Ch::Sender<Cmd>>
+    where
+        Ch::Sender<Cmd>: Clone,
+    {
+        Submitter::new(self.send_cmd.clone(), self.gate.clone())
+    }
+}
+
+impl<Cmd, const N: usize, Ch> CommandRunner for DetachedAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    CmdRst<Cmd>: 'static,
+    Ch: SharedChannel,
+{
+    type Cmd = Cmd;
+    type SendAck = Result<(), SendError<Cmd>>;
+    type CloseResult = CloseReport<Cmd, DetachedRunner<Cmd, Ch>, CmdRst<Cmd>>;
+    unsafe fn with_config(config: &RunnerConfig) -> Self {
+        unsafe { Self::with_sink(Discard, config) }
+    }
+    fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
+        self.send_cmd.send_t(cmd).map_err(Into::into)
+    }
+    fn close_with(self, mut s: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
+        self.gate.close();
+        for _ in 0..N {
+            // Sending only fails once every runner stopped, which joining them reports
+            if self.send(s.get()).is_err() {
+                break;
+            }
+        }
+        CloseReport::join(self.runners)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> CloseTimeout for DetachedAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    CmdRst<Cmd>: 'static,
+    Ch: SharedChannel,
+{
+    type Runner = DetachedRunner<Cmd, Ch>;
+    type Rst = CmdRst<Cmd>;
+    fn close_timeout_with(
+        self,
+        mut s: impl crate::StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        for _ in 0..N {
+            // Not sent in time, or every runner stopped, which joining them reports
+            if send_until(&self.send_cmd, s.get(), deadline).is_err() {
+                break;
+            }
+        }
+        TimeoutReport::join_until(self.runners, deadline)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> Runners for DetachedAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    const RUNNERS: usize = N;
+}
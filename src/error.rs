@@ -0,0 +1,197 @@
+use crossbeam_channel as mpmc;
+use std::any::Any;
+use std::convert::Infallible;
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+
+/// A command could not be sent because the runners it was meant for have stopped
+///
+/// The command is given back.
+#[derive(Debug)]
+pub struct SendError<Cmd>(pub Cmd);
+
+impl<Cmd> SendError<Cmd> {
+    #[must_use]
+    pub fn into_inner(self) -> Cmd {
+        self.0
+    }
+}
+
+impl<Cmd> fmt::Display for SendError<Cmd> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to send command, the runners have stopped")
+    }
+}
+
+impl<Cmd: fmt::Debug> std::error::Error for SendError<Cmd> {}
+
+impl<T> From<mpsc::SendError<T>> for SendError<T> {
+    fn from(e: mpsc::SendError<T>) -> Self {
+        Self(e.0)
+    }
+}
+
+impl<T> From<mpmc::SendError<T>> for SendError<T> {
+    fn from(e: mpmc::SendError<T>) -> Self {
+        Self(e.0)
+    }
+}
+
+#[cfg(feature = "flume")]
+impl<T> From<flume::SendError<T>> for SendError<T> {
+    fn from(e: flume::SendError<T>) -> Self {
+        Self(e.0)
+    }
+}
+
+impl<T> From<Infallible> for SendError<T> {
+    fn from(e: Infallible) -> Self {
+        match e {}
+    }
+}
+
+/// Why a runner stopped, other than being asked to
+///
+/// `Rst` is the type of the results the runner delivers.
+#[derive(Debug)]
+pub enum EventLoopError<Rst> {
+    /// Commands could no longer be received
+    Recv,
+    /// The result could not be delivered, so it's given back
+    ///
+    /// Only a [`Pipeline`](crate::pipeline::Pipeline)'s stages stop like this, once the next
+    /// stage is gone. The managers' runners count a result no one can receive as abandoned, see
+    /// [`RunnerConfig::on_abandoned`](crate::RunnerConfig::on_abandoned), and keep going.
+    Send(Rst),
+    /// The runner's thread could not be configured
+    Setup(io::Error),
+    Panic(Box<dyn Any + Send>),
+}
+
+impl<Rst> fmt::Display for EventLoopError<Rst> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Recv => write!(f, "Failed to recieve"),
+            Self::Send(..) => write!(f, "Failed to deliver result"),
+            Self::Setup(e) => write!(f, "Failed to configure worker: {e}"),
+            Self::Panic(..) => write!(f, "Worker panicked"),
+        }
+    }
+}
+
+impl<Rst: fmt::Debug> std::error::Error for EventLoopError<Rst> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Setup(e) => Some(e),
+            Self::Recv | Self::Send(..) | Self::Panic(..) => None,
+        }
+    }
+}
+
+impl<Rst> From<io::Error> for EventLoopError<Rst> {
+    fn from(e: io::Error) -> Self {
+        Self::Setup(e)
+    }
+}
+
+/// Failure to close a manager
+#[derive(Debug)]
+pub enum CloseError<Cmd, Rst> {
+    /// The stop command could not be sent
+    ///
+    /// The native managers still join their runners then, and report why they stopped as
+    /// [`CloseError::Worker`] instead.
+    Send(SendError<Cmd>),
+    /// The runner stopped by itself
+    Worker(EventLoopError<Rst>),
+}
+
+impl<Cmd, Rst> fmt::Display for CloseError<Cmd, Rst> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Send(..) => write!(f, "Failed to send stop command"),
+            Self::Worker(e) => write!(f, "Worker failed with: {e}"),
+        }
+    }
+}
+
+impl<Cmd, Rst> std::error::Error for CloseError<Cmd, Rst>
+where
+    Cmd: fmt::Debug + 'static,
+    Rst: fmt::Debug + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Send(e) => Some(e),
+            Self::Worker(e) => Some(e),
+        }
+    }
+}
+
+impl<Cmd, Rst> From<SendError<Cmd>> for CloseError<Cmd, Rst> {
+    fn from(e: SendError<Cmd>) -> Self {
+        Self::Send(e)
+    }
+}
+
+impl<Cmd, Rst> From<EventLoopError<Rst>> for CloseError<Cmd, Rst> {
+    fn from(e: EventLoopError<Rst>) -> Self {
+        Self::Worker(e)
+    }
+}
+
+/// A [`CloseError`] of a manager whose type was erased, e.g. by the
+/// [`shutdown`](crate::shutdown) registry, which can be downcast back to it
+pub struct AnyCloseError {
+    message: String,
+    error: Box<dyn Any + Send>,
+}
+
+impl<Cmd, Rst> From<CloseError<Cmd, Rst>> for AnyCloseError
+where
+    Cmd: Send + 'static,
+    Rst: Send + 'static,
+{
+    fn from(e: CloseError<Cmd, Rst>) -> Self {
+        Self {
+            message: e.to_string(),
+            error: Box::new(e),
+        }
+    }
+}
+
+impl AnyCloseError {
+    /// Gives the typed error back, if the manager's commands are `Cmd` and it's results `Rst`
+    ///
+    /// # Errors
+    /// Gives `self` back if the error has another type.
+    pub fn downcast<Cmd: 'static, Rst: 'static>(self) -> Result<CloseError<Cmd, Rst>, Self> {
+        match self.error.downcast() {
+            Ok(e) => Ok(*e),
+            Err(error) => Err(Self {
+                message: self.message,
+                error,
+            }),
+        }
+    }
+
+    #[must_use]
+    pub fn downcast_ref<Cmd: 'static, Rst: 'static>(&self) -> Option<&CloseError<Cmd, Rst>> {
+        self.error.downcast_ref()
+    }
+}
+
+impl fmt::Debug for AnyCloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AnyCloseError").field(&self.message).finish()
+    }
+}
+
+impl fmt::Display for AnyCloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AnyCloseError {}
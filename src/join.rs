@@ -0,0 +1,195 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// Indices of the links that were woken since the last check
+struct Wakeups {
+    thread: Thread,
+    ready: Mutex<Vec<usize>>,
+}
+
+impl Wakeups {
+    fn take(&self) -> Vec<usize> {
+        std::mem::take(&mut *self.ready.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+struct LinkWaker {
+    index: usize,
+    wakeups: Arc<Wakeups>,
+}
+
+impl Wake for LinkWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakeups
+            .ready
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(self.index);
+        self.wakeups.thread.unpark();
+    }
+}
+
+/// Parks the calling thread until one of the links it polled is woken, so only that link is
+/// checked again
+struct Waiter {
+    wakeups: Arc<Wakeups>,
+    /// One per link, so polling a link again doesn't allocate a new one
+    wakers: Vec<Waker>,
+    deadline: Option<Instant>,
+}
+
+impl Waiter {
+    fn new(links: usize, deadline: Option<Instant>) -> Self {
+        let wakeups = Arc::new(Wakeups {
+            thread: thread::current(),
+            ready: Mutex::default(),
+        });
+        let wakers = (0..links)
+            .map(|index| {
+                Waker::from(Arc::new(LinkWaker {
+                    index,
+                    wakeups: wakeups.clone(),
+                }))
+            })
+            .collect();
+        Self {
+            wakeups,
+            wakers,
+            deadline,
+        }
+    }
+
+    fn poll<L: Future + Unpin>(&self, index: usize, link: &mut L) -> Poll<L::Output> {
+        Pin::new(link).poll(&mut Context::from_waker(&self.wakers[index]))
+    }
+
+    /// Parks until a link is woken, returning the woken ones, or none once the deadline passes
+    fn wait(&self) -> Option<Vec<usize>> {
+        loop {
+            let ready = self.wakeups.take();
+            if !ready.is_empty() {
+                return Some(ready);
+            }
+            match self.deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let left = deadline.checked_duration_since(Instant::now())?;
+                    thread::park_timeout(left);
+                }
+            }
+        }
+    }
+
+    fn first<L: Future + Unpin>(&self, links: &mut Vec<L>) -> Option<(usize, L::Output)> {
+        let mut check: Vec<usize> = (0..links.len()).collect();
+        loop {
+            for index in check {
+                // A link may be woken more than once
+                let Some(link) = links.get_mut(index) else {
+                    continue;
+                };
+                if let Poll::Ready(r) = self.poll(index, link) {
+                    links.remove(index);
+                    return Some((index, r));
+                }
+            }
+            check = self.wait()?;
+        }
+    }
+}
+
+/// Waits for every link, returning their results in the same order as the links
+///
+/// Links are any [`Future`] that can be moved while polled, such as a [`Link`](crate::link::Link),
+/// which an actor's [`Reply`](crate::actor::Reply) also is.
+pub fn join_all<L>(links: impl IntoIterator<Item = L>) -> Vec<L::Output>
+where
+    L: Future + Unpin,
+{
+    let mut links: Vec<_> = links.into_iter().map(Some).collect();
+    let waiter = Waiter::new(links.len(), None);
+    let mut results: Vec<_> = links.iter().map(|_| None).collect();
+    let mut pending = links.len();
+    let mut check: Vec<usize> = (0..links.len()).collect();
+    loop {
+        for index in check {
+            let Some(link) = &mut links[index] else {
+                continue;
+            };
+            if let Poll::Ready(r) = waiter.poll(index, link) {
+                links[index] = None;
+                results[index] = Some(r);
+                pending -= 1;
+            }
+        }
+        if pending == 0 {
+            break;
+        }
+        // Without a deadline, waiting always returns the woken links
+        check = waiter.wait().unwrap_or_default();
+    }
+    results.into_iter().flatten().collect()
+}
+
+/// Waits for the first link to complete, which is removed from `links`
+///
+/// Returns the index the completed link had in `links` and it's result, or `None` if there are
+/// no links. Calling this repeatedly gives the results in the order they complete.
+pub fn select_any<L>(links: &mut Vec<L>) -> Option<(usize, L::Output)>
+where
+    L: Future + Unpin,
+{
+    if links.is_empty() {
+        return None;
+    }
+    Waiter::new(links.len(), None).first(links)
+}
+
+/// Why [`race_with_timeout`] returned without a result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceError {
+    /// There were no links
+    Empty,
+    Timeout,
+}
+
+impl fmt::Display for RaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "No links to wait on"),
+            Self::Timeout => write!(f, "Timed out waiting for links"),
+        }
+    }
+}
+
+impl std::error::Error for RaceError {}
+
+/// Same as [`select_any`], but gives up after `timeout`
+///
+/// A `timeout` too long to ever be reached waits for the first link, like [`select_any`].
+///
+/// # Errors
+/// Fails if there are no links, or none completes within `timeout`.
+pub fn race_with_timeout<L>(
+    links: &mut Vec<L>,
+    timeout: Duration,
+) -> Result<(usize, L::Output), RaceError>
+where
+    L: Future + Unpin,
+{
+    if links.is_empty() {
+        return Err(RaceError::Empty);
+    }
+    // A deadline too far to represent is never reached
+    Waiter::new(links.len(), Instant::now().checked_add(timeout))
+        .first(links)
+        .ok_or(RaceError::Timeout)
+}
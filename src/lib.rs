@@ -1,18 +1,49 @@
 use crossbeam_channel as mpmc;
+use error::{CloseError, EventLoopError, SendError};
 use std::fmt;
 use std::sync::mpsc;
+use std::time::Duration;
 
 #[cfg(test)]
 mod test;
 
+mod close;
+pub use close::{CloseReport, CloseTimeout, TimeoutReport, Unfinished};
+
+mod config;
+pub use config::RunnerConfig;
+
+pub mod actor;
+pub mod channel;
+pub mod detached;
+pub mod error;
+pub mod join;
+pub mod limit;
+pub mod link;
 pub mod oneshot;
 pub mod oneshot_pool;
 pub mod oneshot_single;
 
+pub mod pipeline;
+
+mod select;
+pub use select::{ResultQueue, Select, SelectError};
+
 pub(crate) mod queue;
+pub mod queue_envelope;
+pub mod queue_keyed;
 pub mod queue_pool;
 pub mod queue_single;
 
+pub mod runtime;
+
+#[cfg(feature = "signal")]
+pub mod shutdown;
+pub mod submit;
+pub(crate) mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+
 #[derive(Debug)]
 pub enum ActionResult<Rst> {
     Normal(Rst),
@@ -22,6 +53,11 @@ pub enum ActionResult<Rst> {
 pub trait Command: Send + Sync + 'static {
     type Result: Send + fmt::Debug;
     fn execute(self) -> ActionResult<Self::Result>;
+
+    /// Class of the command, for the limits set with [`RunnerConfig::limit`]
+    fn class(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 /// Creates a command that would halt the command runner>
@@ -39,9 +75,9 @@ pub trait CommandRunner {
     /// The command it accepts
     type Cmd: Command;
     /// Result of sending a command to a runner
-    type SendAck;
+    type SendAck: SendOutcome<Self::Cmd>;
     /// The result of halting a runner
-    type CloseResult;
+    type CloseResult: CloseOutcome<Cmd = Self::Cmd>;
 
     /// # Safety
     /// Since this *should* start another thread, it's only safe to call this if
@@ -49,7 +85,20 @@ pub trait CommandRunner {
     ///
     /// [`CommandRunner::scope_with`] and [`CommandRunner::scope`] always call `close` on the
     /// runner.
-    unsafe fn new() -> Self;
+    #[must_use]
+    unsafe fn new() -> Self
+    where
+        Self: Sized,
+    {
+        unsafe { Self::with_config(&RunnerConfig::default()) }
+    }
+    /// Same as [`CommandRunner::new`], but the runner threads are spawned following `config`
+    ///
+    /// # Safety
+    /// See [`CommandRunner::new`]
+    unsafe fn with_config(config: &RunnerConfig) -> Self
+    where
+        Self: Sized;
     fn send(&self, cmd: Self::Cmd) -> Self::SendAck;
     fn close_with(self, s: impl StopRunner<Self::Cmd>) -> Self::CloseResult;
     fn close(self) -> Self::CloseResult
@@ -65,7 +114,18 @@ pub trait CommandRunner {
     where
         Self: Sized,
     {
-        let runner = unsafe { Self::new() };
+        Self::scope_with_config(&RunnerConfig::default(), closer, f)
+    }
+
+    fn scope_with_config(
+        config: &RunnerConfig,
+        closer: impl StopRunner<Self::Cmd>,
+        f: impl FnOnce(&Self),
+    ) -> Self::CloseResult
+    where
+        Self: Sized,
+    {
+        let runner = unsafe { Self::with_config(config) };
         f(&runner);
         runner.close_with(closer)
     }
@@ -77,6 +137,73 @@ pub trait CommandRunner {
     {
         Self::scope_with(SimpleCloser, f)
     }
+
+    fn scope_config(config: &RunnerConfig, f: impl FnOnce(&Self)) -> Self::CloseResult
+    where
+        Self: Sized,
+        Self::Cmd: SimpleStop,
+    {
+        Self::scope_with_config(config, SimpleCloser, f)
+    }
+}
+
+/// What a manager's [`CommandRunner::send`] returns, so generic code can tell if it failed
+pub trait SendOutcome<Cmd> {
+    /// What's given back for a command that was sent, e.g. a link to it's result
+    type Ack;
+    /// # Errors
+    /// Fails if the command was not sent, giving it back.
+    fn into_result(self) -> Result<Self::Ack, SendError<Cmd>>;
+}
+
+impl<T, Cmd> SendOutcome<Cmd> for Result<T, SendError<Cmd>> {
+    type Ack = T;
+    fn into_result(self) -> Result<T, SendError<Cmd>> {
+        self
+    }
+}
+
+/// A runner, or every runner of a pool, once it's thread was joined
+pub trait Stopped<Rst> {
+    /// Why each runner that stopped by itself did so
+    fn failures(self) -> Vec<EventLoopError<Rst>>;
+}
+
+impl<T, Rst, const N: usize> Stopped<Rst> for [Result<T, EventLoopError<Rst>>; N]
+where
+    T: Stopped<Rst>,
+{
+    fn failures(self) -> Vec<EventLoopError<Rst>> {
+        self.into_iter()
+            .flat_map(|r| match r {
+                Ok(runner) => runner.failures(),
+                Err(e) => vec![e],
+            })
+            .collect()
+    }
+}
+
+/// What a manager's [`CommandRunner::close_with`] returns, so generic code can inspect it
+pub trait CloseOutcome {
+    type Cmd;
+    /// Type of the results the runners deliver
+    type Rst;
+    /// Every failure while closing, empty if every runner stopped when asked to
+    fn into_errors(self) -> Vec<CloseError<Self::Cmd, Self::Rst>>;
+}
+
+impl<T, Cmd, Rst> CloseOutcome for Result<T, CloseError<Cmd, Rst>>
+where
+    T: Stopped<Rst>,
+{
+    type Cmd = Cmd;
+    type Rst = Rst;
+    fn into_errors(self) -> Vec<CloseError<Cmd, Rst>> {
+        match self {
+            Ok(stopped) => stopped.failures().into_iter().map(Into::into).collect(),
+            Err(e) => vec![e],
+        }
+    }
 }
 
 impl<C> StopRunner<C> for SimpleCloser
@@ -91,10 +218,18 @@ where
 pub(crate) type CmdRst<C> = <C as Command>::Result;
 
 pub trait ChanSend<T> {
-    type Err;
+    type Err: Into<SendError<T>>;
     /// # Errors
     /// associated type to account for send errors
     fn send_t(&self, t: T) -> Result<(), Self::Err>;
+    /// Sends with [`ChanSend::send_t`] unless overridden, so it only suits unbounded channels
+    ///
+    /// # Errors
+    /// Fails if the channel is full or disconnected, giving `t` back.
+    fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        self.send_t(t)
+            .map_err(|e| mpmc::TrySendError::Disconnected(e.into().0))
+    }
 }
 
 pub trait ChanRecv<T> {
@@ -102,6 +237,23 @@ pub trait ChanRecv<T> {
     /// # Errors
     /// associated type to account for recv errors
     fn recv_t(&self) -> Result<T, Self::Err>;
+    /// Never receives unless overridden, since receiving could block
+    ///
+    /// # Errors
+    /// Fails if the channel is empty or disconnected.
+    fn try_recv_t(&self) -> Result<T, mpmc::TryRecvError> {
+        Err(mpmc::TryRecvError::Empty)
+    }
+    /// Waits with [`ChanRecv::recv_t`] unless overridden, so it never times out. A runner then
+    /// only hands out the commands put off by it's [limits](crate::limit) once another command
+    /// arrives.
+    ///
+    /// # Errors
+    /// Fails if nothing is received within `timeout`, or the channel is disconnected.
+    fn recv_timeout_t(&self, _timeout: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+        self.recv_t()
+            .map_err(|_| mpmc::RecvTimeoutError::Disconnected)
+    }
 }
 
 impl<T> ChanSend<T> for mpsc::Sender<T> {
@@ -109,6 +261,11 @@ impl<T> ChanSend<T> for mpsc::Sender<T> {
     fn send_t(&self, t: T) -> Result<(), Self::Err> {
         self.send(t)
     }
+    fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        // Unbounded, so it's never full
+        self.send(t)
+            .map_err(|e| mpmc::TrySendError::Disconnected(e.0))
+    }
 }
 
 impl<T> ChanRecv<T> for mpsc::Receiver<T> {
@@ -116,6 +273,18 @@ impl<T> ChanRecv<T> for mpsc::Receiver<T> {
     fn recv_t(&self) -> Result<T, Self::Err> {
         self.recv()
     }
+    fn try_recv_t(&self) -> Result<T, mpmc::TryRecvError> {
+        self.try_recv().map_err(|e| match e {
+            mpsc::TryRecvError::Empty => mpmc::TryRecvError::Empty,
+            mpsc::TryRecvError::Disconnected => mpmc::TryRecvError::Disconnected,
+        })
+    }
+    fn recv_timeout_t(&self, timeout: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+        self.recv_timeout(timeout).map_err(|e| match e {
+            mpsc::RecvTimeoutError::Timeout => mpmc::RecvTimeoutError::Timeout,
+            mpsc::RecvTimeoutError::Disconnected => mpmc::RecvTimeoutError::Disconnected,
+        })
+    }
 }
 
 impl<T> ChanSend<T> for mpmc::Sender<T> {
@@ -123,6 +292,9 @@ impl<T> ChanSend<T> for mpmc::Sender<T> {
     fn send_t(&self, t: T) -> Result<(), Self::Err> {
         self.send(t)
     }
+    fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        self.try_send(t)
+    }
 }
 
 impl<T> ChanRecv<T> for mpmc::Receiver<T> {
@@ -130,4 +302,44 @@ impl<T> ChanRecv<T> for mpmc::Receiver<T> {
     fn recv_t(&self) -> Result<T, Self::Err> {
         self.recv()
     }
+    fn try_recv_t(&self) -> Result<T, mpmc::TryRecvError> {
+        self.try_recv()
+    }
+    fn recv_timeout_t(&self, timeout: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+        self.recv_timeout(timeout)
+    }
+}
+
+#[cfg(feature = "flume")]
+impl<T> ChanSend<T> for flume::Sender<T> {
+    type Err = flume::SendError<T>;
+    fn send_t(&self, t: T) -> Result<(), Self::Err> {
+        self.send(t)
+    }
+    fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+        self.try_send(t).map_err(|e| match e {
+            flume::TrySendError::Full(t) => mpmc::TrySendError::Full(t),
+            flume::TrySendError::Disconnected(t) => mpmc::TrySendError::Disconnected(t),
+        })
+    }
+}
+
+#[cfg(feature = "flume")]
+impl<T> ChanRecv<T> for flume::Receiver<T> {
+    type Err = flume::RecvError;
+    fn recv_t(&self) -> Result<T, Self::Err> {
+        self.recv()
+    }
+    fn try_recv_t(&self) -> Result<T, mpmc::TryRecvError> {
+        self.try_recv().map_err(|e| match e {
+            flume::TryRecvError::Empty => mpmc::TryRecvError::Empty,
+            flume::TryRecvError::Disconnected => mpmc::TryRecvError::Disconnected,
+        })
+    }
+    fn recv_timeout_t(&self, timeout: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+        self.recv_timeout(timeout).map_err(|e| match e {
+            flume::RecvTimeoutError::Timeout => mpmc::RecvTimeoutError::Timeout,
+            flume::RecvTimeoutError::Disconnected => mpmc::RecvTimeoutError::Disconnected,
+        })
+    }
 }
@@ -0,0 +1,331 @@
+//! Rate limits and concurrency caps for classes of commands, set with [`RunnerConfig::limit`]
+//!
+//! [`RunnerConfig::limit`]: crate::RunnerConfig::limit
+use crate::ChanRecv;
+use crossbeam_channel as mpmc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How often a runner checks for a permit while receiving, when a class of the commands it put
+/// off is at it's cap
+const POLL: Duration = Duration::from_millis(1);
+
+/// Limits of a class of commands, shared by every runner they're set for
+///
+/// The rate is a token bucket, which lets `burst` commands through at once before spacing them
+/// out. With a burst of 1 it's a leaky bucket instead, commands are always spaced out evenly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limit {
+    /// Time between two commands, and how many can go through at once
+    rate: Option<(Duration, u32)>,
+    max_in_flight: Option<usize>,
+}
+
+impl Limit {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// At most `count` commands are executed every `per`, with a burst of `count`
+    ///
+    /// # Panics
+    /// Panics if `count` is zero.
+    #[must_use]
+    pub fn rate(mut self, count: u32, per: Duration) -> Self {
+        assert!(count > 0, "a rate needs at least one command");
+        self.rate = Some((per / count, count));
+        self
+    }
+
+    /// How many commands can go through at once, before the rate spaces them out
+    ///
+    /// # Panics
+    /// Panics if no rate was set, or `burst` is zero.
+    #[must_use]
+    pub fn burst(mut self, burst: u32) -> Self {
+        assert!(burst > 0, "a burst needs at least one command");
+        let (interval, _) = self.rate.expect("a burst needs a rate");
+        self.rate = Some((interval, burst));
+        self
+    }
+
+    /// At most `max` commands are executed at the same time, across every runner
+    ///
+    /// # Panics
+    /// Panics if `max` is zero.
+    #[must_use]
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        assert!(max > 0, "a cap needs at least one command");
+        self.max_in_flight = Some(max);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// When the bucket is empty again, the theoretical arrival time of GCRA
+    empty_at: Option<Instant>,
+    in_flight: usize,
+}
+
+/// `t + d`, or the latest instant after `t` that can be represented
+fn saturating_add(mut t: Instant, d: Duration) -> Instant {
+    let mut left = d;
+    let mut step = d;
+    // Adds as much of what's left as fits, halving the step each time it doesn't
+    while !left.is_zero() && !step.is_zero() {
+        step = step.min(left);
+        match t.checked_add(step) {
+            Some(sum) => {
+                t = sum;
+                left -= step;
+            }
+            None => step /= 2,
+        }
+    }
+    t
+}
+
+/// Counts the permits released by the classes of a config, so runners can wait for one
+#[derive(Debug, Default)]
+struct Releases {
+    count: Mutex<u64>,
+    released: Condvar,
+}
+
+impl Releases {
+    fn count(&self) -> u64 {
+        *self.count.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn release(&self) {
+        *self.count.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        self.released.notify_all();
+    }
+
+    /// Blocks until a permit is released after `seen` were, or `timeout` passes
+    fn wait(&self, seen: u64, timeout: Duration) {
+        let count = self.count.lock().unwrap_or_else(PoisonError::into_inner);
+        // Whether it was released or timed out, the caller checks the classes again
+        drop(
+            self.released
+                .wait_timeout_while(count, timeout, |count| *count == seen)
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Class {
+    limit: Limit,
+    state: Mutex<State>,
+    releases: Arc<Releases>,
+}
+
+impl Class {
+    fn new(limit: Limit, releases: Arc<Releases>) -> Self {
+        Self {
+            limit,
+            state: Mutex::default(),
+            releases,
+        }
+    }
+
+    /// Takes a permit, or tells how long until one may be available, `None` if that's once a
+    /// command of the class is done
+    ///
+    /// # Errors
+    /// Fails if the class is over one of it's limits.
+    fn acquire(self: &Arc<Self>) -> Result<Option<Permit>, Option<Duration>> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(max) = self.limit.max_in_flight
+            && state.in_flight >= max
+        {
+            return Err(None);
+        }
+        if let Some((interval, burst)) = self.limit.rate {
+            let now = Instant::now();
+            let empty_at = state.empty_at.map_or(now, |t| t.max(now));
+            let tolerance = interval.checked_mul(burst - 1).unwrap_or(Duration::MAX);
+            if let Some(allowed_at) = empty_at.checked_sub(tolerance)
+                && allowed_at > now
+            {
+                return Err(Some(allowed_at - now));
+            }
+            state.empty_at = Some(saturating_add(empty_at, interval));
+        }
+        if self.limit.max_in_flight.is_none() {
+            return Ok(None);
+        }
+        state.in_flight += 1;
+        Ok(Some(Permit {
+            class: self.clone(),
+        }))
+    }
+}
+
+/// A command of a capped class is in flight, until it's dropped
+pub(crate) struct Permit {
+    class: Arc<Class>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self
+            .class
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        state.in_flight -= 1;
+        drop(state);
+        self.class.releases.release();
+    }
+}
+
+/// The classes limited by a config, whose runners wait on the same [`Releases`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Classes {
+    classes: HashMap<&'static str, Arc<Class>>,
+    releases: Arc<Releases>,
+}
+
+impl Classes {
+    pub(crate) fn insert(&mut self, class: &'static str, limit: Limit) {
+        let class_limit = Class::new(limit, self.releases.clone());
+        self.classes.insert(class, Arc::new(class_limit));
+    }
+}
+
+/// Hands a runner the commands it receives, putting off the ones over their class's limits
+pub(crate) struct Dispatch<T> {
+    classes: Classes,
+    deferred: VecDeque<(&'static str, T)>,
+    /// Waits for a command's permit instead of putting it off, so none can overtake it
+    in_order: bool,
+}
+
+impl<T> Dispatch<T> {
+    pub(crate) fn new(classes: Classes, in_order: bool) -> Self {
+        Self {
+            classes,
+            deferred: VecDeque::new(),
+            in_order,
+        }
+    }
+
+    /// Blocks until `class` has a permit
+    fn wait_for(&self, class: &Arc<Class>) -> Option<Permit> {
+        loop {
+            // Read before trying, so a release in between isn't missed
+            let seen = self.classes.releases.count();
+            match class.acquire() {
+                Ok(permit) => return permit,
+                Err(wait) => self
+                    .classes
+                    .releases
+                    .wait(seen, wait.unwrap_or(Duration::MAX)),
+            }
+        }
+    }
+
+    /// The oldest command put off whose class has a permit, or how long to wait for one
+    ///
+    /// A class at it's cap has a permit once a command of it is done, which releases it.
+    ///
+    /// # Errors
+    /// Fails if no class of the deferred commands has a permit.
+    fn take_deferred(&mut self) -> Result<Option<(T, Option<Permit>)>, Duration> {
+        let mut wait: Option<Duration> = None;
+        let mut refused = Vec::new();
+        for i in 0..self.deferred.len() {
+            let class = self.deferred[i].0;
+            // Commands of a class are executed in the order they were received
+            if refused.contains(&class) {
+                continue;
+            }
+            match self.classes.classes[class].acquire() {
+                Ok(permit) => {
+                    let t = self.deferred.remove(i).map(|(_, t)| (t, permit));
+                    return Ok(t);
+                }
+                Err(hint) => {
+                    let hint = hint.unwrap_or(POLL);
+                    wait = Some(wait.map_or(hint, |w| w.min(hint)));
+                    refused.push(class);
+                }
+            }
+        }
+        wait.map_or(Ok(None), Err)
+    }
+
+    /// Next command to execute, a deferred one if it's class has a permit, or else one received
+    /// from `chan`
+    ///
+    /// Commands of other classes keep being received while the deferred ones wait, so the runner
+    /// only waits on `chan` until a deferred command may have a permit.
+    ///
+    /// # Errors
+    /// Fails once `chan` is disconnected and every deferred command was handed out.
+    pub(crate) fn next(
+        &mut self,
+        chan: &impl ChanRecv<T>,
+        class: impl Fn(&T) -> Option<&'static str>,
+    ) -> Result<(T, Option<Permit>), mpmc::RecvError> {
+        if self.classes.classes.is_empty() {
+            return chan
+                .recv_t()
+                .map(|t| (t, None))
+                .map_err(|_| mpmc::RecvError);
+        }
+        if self.in_order {
+            let t = chan.recv_t().map_err(|_| mpmc::RecvError)?;
+            let permit = match class(&t).and_then(|c| self.classes.classes.get(c)) {
+                Some(limited) => self.wait_for(limited),
+                None => None,
+            };
+            return Ok((t, permit));
+        }
+        loop {
+            let received = match self.take_deferred() {
+                Ok(Some(next)) => return Ok(next),
+                Ok(None) => chan
+                    .recv_t()
+                    .map_err(|_| mpmc::RecvTimeoutError::Disconnected),
+                Err(wait) => chan.recv_timeout_t(wait),
+            };
+            let t = match received {
+                Ok(t) => t,
+                Err(mpmc::RecvTimeoutError::Timeout) => continue,
+                // The commands put off are still handed out, they were received before
+                Err(mpmc::RecvTimeoutError::Disconnected) => {
+                    return self.deferred().ok_or(mpmc::RecvError);
+                }
+            };
+            let Some(name) = class(&t).filter(|c| self.classes.classes.contains_key(c)) else {
+                return Ok((t, None));
+            };
+            if self.deferred.iter().any(|(c, _)| *c == name) {
+                self.deferred.push_back((name, t));
+                continue;
+            }
+            match self.classes.classes[name].acquire() {
+                Ok(permit) => return Ok((t, permit)),
+                Err(_) => self.deferred.push_back((name, t)),
+            }
+        }
+    }
+
+    /// Blocks until a deferred command can be executed, returns `None` once there are none
+    pub(crate) fn deferred(&mut self) -> Option<(T, Option<Permit>)> {
+        loop {
+            let seen = self.classes.releases.count();
+            match self.take_deferred() {
+                Ok(next) => return next,
+                // Nothing else to receive, so a release wakes it up before the poll is over
+                Err(wait) => self.classes.releases.wait(seen, wait),
+            }
+        }
+    }
+}
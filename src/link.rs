@@ -0,0 +1,72 @@
+use crossbeam_channel as mpmc;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Creates a single use channel, for the result of one command
+pub(crate) fn link<T>() -> (LinkSender<T>, Link<T>) {
+    let (tx, rx) = oneshot::channel();
+    (LinkSender(tx), Link(rx))
+}
+
+/// Runner's side of a [`Link`]
+pub(crate) struct LinkSender<T>(oneshot::Sender<T>);
+
+impl<T> LinkSender<T> {
+    /// # Errors
+    /// Gives `t` back if the link was dropped.
+    pub(crate) fn send(self, t: T) -> Result<(), T> {
+        self.0.send(t).map_err(oneshot::SendError::into_inner)
+    }
+}
+
+/// Receives the result of a single command
+///
+/// It can be waited on by blocking, or polled as a [`Future`]. A link can be sent to another
+/// thread, but not shared between threads, since only one of them could receive the result.
+pub struct Link<T>(oneshot::Receiver<T>);
+
+impl<T> Link<T> {
+    /// Blocks until the result is sent
+    ///
+    /// # Errors
+    /// Fails if the runner stopped without sending the result.
+    pub fn recv(self) -> Result<T, mpmc::RecvError> {
+        self.0.recv().map_err(|_| mpmc::RecvError)
+    }
+
+    /// # Errors
+    /// Fails if the result wasn't sent yet, or the runner stopped without sending it.
+    pub fn try_recv(&self) -> Result<T, mpmc::TryRecvError> {
+        self.0.try_recv().map_err(|e| match e {
+            oneshot::TryRecvError::Empty => mpmc::TryRecvError::Empty,
+            oneshot::TryRecvError::Disconnected => mpmc::TryRecvError::Disconnected,
+        })
+    }
+
+    /// # Errors
+    /// Fails if the result isn't sent within `timeout`, or the runner stopped without sending
+    /// it.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+        self.0.recv_timeout(timeout).map_err(|e| match e {
+            oneshot::RecvTimeoutError::Timeout => mpmc::RecvTimeoutError::Timeout,
+            oneshot::RecvTimeoutError::Disconnected => mpmc::RecvTimeoutError::Disconnected,
+        })
+    }
+}
+
+impl<T> Future for Link<T> {
+    type Output = Result<T, mpmc::RecvError>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0)
+            .poll(cx)
+            .map(|r| r.map_err(|_| mpmc::RecvError))
+    }
+}
+
+impl<T> std::fmt::Debug for Link<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Link").finish_non_exhaustive()
+    }
+}
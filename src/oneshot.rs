@@ -1,42 +1,42 @@
-use std::fmt;
+use crate::sync::thread::JoinHandle;
 use std::marker::PhantomData;
-use std::thread::JoinHandle;
+use std::panic::{self, AssertUnwindSafe};
 
-use crate::{ActionResult, ChanRecv, CmdRst, Command};
+use crate::config::Abandoned;
+use crate::error::EventLoopError;
+use crate::link::{self, Link, LinkSender};
+use crate::{ActionResult, ChanRecv, CmdRst, Command, RunnerConfig, Stopped};
 
-pub(crate) type InternalCommandLink<Cmd> = oneshot::Sender<CmdRst<Cmd>>;
-pub(crate) type ExternalCommandLink<Cmd> = oneshot::Receiver<CmdRst<Cmd>>;
+pub(crate) type InternalCommandLink<Cmd> = LinkSender<CmdRst<Cmd>>;
+/// Receives the result of a command sent to a linked manager
+pub type ExternalCommandLink<Cmd> = Link<CmdRst<Cmd>>;
+pub(crate) type Callback<Cmd> = Box<dyn FnOnce(CmdRst<Cmd>) + Send>;
 
-pub struct QueuedCommand<Cmd>
+/// How a runner hands the result back
+pub(crate) enum Reply<Cmd>
 where
     Cmd: Command,
 {
-    pub(crate) cmd: Cmd,
-    pub(crate) chan: InternalCommandLink<Cmd>,
+    Link(InternalCommandLink<Cmd>),
+    /// Called on the runner's thread
+    Callback(Callback<Cmd>),
 }
 
-#[derive(Debug)]
-pub enum OneshotEventLoopError<Cmd>
+impl<Cmd: Command> Reply<Cmd> {
+    pub(crate) fn link() -> (Self, ExternalCommandLink<Cmd>) {
+        let (tx, rx) = link::link();
+        (Self::Link(tx), rx)
+    }
+}
+
+pub struct QueuedCommand<Cmd>
 where
     Cmd: Command,
 {
-    SendErr(CmdRst<Cmd>),
-    RecvErr,
-    ThreadPanic(Box<dyn std::any::Any + Send>),
-}
-
-impl<Cmd: Command + fmt::Debug> fmt::Display for OneshotEventLoopError<Cmd> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::RecvErr => write!(f, "Failed to recieve"),
-            Self::SendErr(t) => write!(f, "Failed to write ({t:?})"),
-            Self::ThreadPanic(..) => write!(f, "Worker panicked"),
-        }
-    }
+    pub(crate) cmd: Cmd,
+    pub(crate) reply: Reply<Cmd>,
 }
 
-impl<Cmd: Command + fmt::Debug> std::error::Error for OneshotEventLoopError<Cmd> {}
-
 impl<Cmd> std::fmt::Debug for QueuedCommand<Cmd>
 where
     Cmd: std::fmt::Debug + Command,
@@ -54,35 +54,84 @@ where
 {
     d: PhantomData<Cmd>,
     pub(crate) reqs: R,
+    abandoned: Abandoned,
+}
+
+impl<Cmd, R, Rst> Stopped<Rst> for OneShotRunner<Cmd, R>
+where
+    Cmd: Command,
+    R: ChanRecv<QueuedCommand<Cmd>>,
+{
+    fn failures(self) -> Vec<EventLoopError<Rst>> {
+        Vec::new()
+    }
 }
 
 impl<Cmd, R> OneShotRunner<Cmd, R>
 where
     Cmd: Command,
     R: ChanRecv<QueuedCommand<Cmd>> + Send + 'static,
-    <R as ChanRecv<QueuedCommand<Cmd>>>::Err: std::fmt::Debug,
 {
-    fn get(&self) -> Result<QueuedCommand<Cmd>, R::Err> {
-        self.reqs.recv_t()
-    }
     fn exec(cmd: Cmd) -> ActionResult<CmdRst<Cmd>> {
         cmd.execute()
     }
-    /// # Panics
-    /// The default runners panic if the channels they're bound to are dropped.
-    pub(crate) fn spawn(rx: R) -> JoinHandle<Result<Self, OneshotEventLoopError<Cmd>>> {
-        std::thread::spawn(move || {
-            let runner = Self {
+    fn deliver(&mut self, reply: Reply<Cmd>, res: CmdRst<Cmd>) {
+        match reply {
+            Reply::Link(chan) => {
+                if let Err(res) = chan.send(res) {
+                    self.abandoned.give(res);
+                }
+            }
+            Reply::Callback(f) => {
+                // The callback is the caller's code, it mustn't take the runner down with it
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| f(res))) {
+                    self.abandoned.panicked(payload);
+                }
+            }
+        }
+    }
+    /// How many results were dropped, because their link was, or their callback panicked
+    #[must_use]
+    pub fn abandoned(&self) -> u64 {
+        self.abandoned.count()
+    }
+    /// A dropped link or a callback that panics doesn't stop the runner, they're handed to
+    /// [`RunnerConfig::on_abandoned`] instead.
+    pub(crate) fn spawn(
+        config: &RunnerConfig,
+        name: &str,
+        index: usize,
+        rx: R,
+    ) -> JoinHandle<Result<Self, EventLoopError<CmdRst<Cmd>>>> {
+        let abandoned = config.abandoned();
+        let mut dispatch = config.dispatch();
+        let tally = config.tally();
+        config.spawn(name, index, move || {
+            let mut runner = Self {
                 reqs: rx,
+                abandoned,
                 d: PhantomData,
             };
             loop {
-                let msg = runner.get().map_err(|_| OneshotEventLoopError::RecvErr)?;
+                let (msg, permit) = dispatch
+                    .next(&runner.reqs, |msg: &QueuedCommand<Cmd>| msg.cmd.class())
+                    .map_err(|_| EventLoopError::Recv)?;
+                tally.received();
                 let r = Self::exec(msg.cmd);
+                drop(permit);
                 let ActionResult::Normal(res) = r else { break };
-                msg.chan
-                    .send(res)
-                    .map_err(|k| OneshotEventLoopError::SendErr(k.into_inner()))?;
+                tally.executed();
+                runner.deliver(msg.reply, res);
+            }
+            // Commands put off by their class's limits are still executed before stopping
+            while let Some((msg, permit)) = dispatch.deferred() {
+                tally.received();
+                let r = Self::exec(msg.cmd);
+                drop(permit);
+                if let ActionResult::Normal(res) = r {
+                    tally.executed();
+                    runner.deliver(msg.reply, res);
+                }
             }
             Ok(runner)
         })
@@ -1,49 +1,137 @@
-use crossbeam_channel as mpmc;
-use std::thread::JoinHandle;
+use crate::sync::thread::JoinHandle;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::oneshot::{ExternalCommandLink, OneShotRunner, OneshotEventLoopError, QueuedCommand};
-use crate::{Command, CommandRunner};
-type MR<Cmd> = mpmc::Receiver<QueuedCommand<Cmd>>;
+use crate::channel::{Channel, Crossbeam, SharedChannel};
+use crate::close::{self, send_until};
+use crate::error::{EventLoopError, SendError};
+use crate::oneshot::{ExternalCommandLink, OneShotRunner, QueuedCommand, Reply};
+use crate::runtime::Runners;
+use crate::submit::{Gate, LinkSubmitter};
+use crate::{
+    ChanSend, CloseReport, CloseTimeout, CmdRst, Command, CommandRunner, RunnerConfig,
+    TimeoutReport,
+};
+type MR<Cmd, Ch> = <Ch as Channel>::Receiver<QueuedCommand<Cmd>>;
+type PoolRunner<Cmd, Ch> = OneShotRunner<Cmd, MR<Cmd, Ch>>;
+type Worker<Cmd, Ch> = JoinHandle<Result<PoolRunner<Cmd, Ch>, EventLoopError<CmdRst<Cmd>>>>;
 
-pub struct OneShotPoolAPI<Cmd, const N: usize>
+/// Commands are queued in a `Ch` [channel](crate::channel), [`crossbeam_channel`]'s by default.
+pub struct OneShotPoolAPI<Cmd, const N: usize, Ch = Crossbeam>
 where
     Cmd: Command,
+    Ch: SharedChannel,
 {
-    cmd_queue: mpmc::Sender<QueuedCommand<Cmd>>,
-    runners: [JoinHandle<Result<OneShotRunner<Cmd, MR<Cmd>>, OneshotEventLoopError<Cmd>>>; N],
+    cmd_queue: Ch::Sender<QueuedCommand<Cmd>>,
+    runners: [Worker<Cmd, Ch>; N],
+    gate: Arc<Gate>,
 }
 
-impl<Cmd, const N: usize> CommandRunner for OneShotPoolAPI<Cmd, N>
+impl<Cmd, const N: usize, Ch> CommandRunner for OneShotPoolAPI<Cmd, N, Ch>
 where
     Cmd: Command,
+    Ch: SharedChannel,
 {
     type Cmd = Cmd;
-    type SendAck = Result<ExternalCommandLink<Cmd>, mpmc::SendError<QueuedCommand<Cmd>>>;
-    type CloseResult = Result<
-        [Result<OneShotRunner<Cmd, MR<Cmd>>, OneshotEventLoopError<Cmd>>; N],
-        mpmc::SendError<QueuedCommand<Cmd>>,
-    >;
-    unsafe fn new() -> Self {
-        let (tx_cmd, rx_cmd) = mpmc::unbounded::<QueuedCommand<Cmd>>();
-        let runners = [(); N].map(|()| OneShotRunner::<Cmd, MR<Cmd>>::spawn(rx_cmd.clone()));
+    type SendAck = Result<ExternalCommandLink<Cmd>, SendError<Cmd>>;
+    type CloseResult = CloseReport<Cmd, PoolRunner<Cmd, Ch>, CmdRst<Cmd>>;
+    unsafe fn with_config(config: &RunnerConfig) -> Self {
+        let (tx_cmd, rx_cmd) = Ch::channel::<QueuedCommand<Cmd>>();
+        let runners = std::array::from_fn(|i| {
+            OneShotRunner::spawn(config, "supera-ospool", i, Ch::share(&rx_cmd))
+        });
         Self {
             cmd_queue: tx_cmd,
             runners,
+            gate: Arc::default(),
         }
     }
     fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
-        let (tx, rx) = oneshot::channel();
-        let msg = QueuedCommand { cmd, chan: tx };
-        self.cmd_queue.send(msg)?;
+        let (reply, rx) = Reply::link();
+        self.send_queued(QueuedCommand { cmd, reply })?;
         Ok(rx)
     }
     fn close_with(self, mut s: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
-        for _ in 0..self.runners.len() {
-            self.send(s.get())?;
+        self.gate.close();
+        for _ in 0..N {
+            // Sending only fails once every runner stopped, which joining them reports
+            if self.send(s.get()).is_err() {
+                break;
+            }
         }
-        Ok(self
-            .runners
-            .map(std::thread::JoinHandle::join)
-            .map(|e| e.map_err(OneshotEventLoopError::ThreadPanic)?))
+        CloseReport::join(self.runners)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> CloseTimeout for OneShotPoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Runner = PoolRunner<Cmd, Ch>;
+    type Rst = CmdRst<Cmd>;
+    fn close_timeout_with(
+        self,
+        mut s: impl crate::StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        for _ in 0..N {
+            let (reply, _) = Reply::link();
+            let stop = QueuedCommand {
+                cmd: s.get(),
+                reply,
+            };
+            // Not sent in time, or every runner stopped, which joining them reports
+            if send_until(&self.cmd_queue, stop, deadline).is_err() {
+                break;
+            }
+        }
+        TimeoutReport::join_until(self.runners, deadline)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> Runners for OneShotPoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    const RUNNERS: usize = N;
+}
+
+impl<Cmd, const N: usize, Ch> OneShotPoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    /// Handle that can send commands from other threads, until the manager is closed
+    #[must_use]
+    pub fn submitter(&self) -> LinkSubmitter<Cmd, Ch::Sender<QueuedCommand<Cmd>>>
+    where
+        Ch::Sender<QueuedCommand<Cmd>>: Clone,
+    {
+        LinkSubmitter::new(self.cmd_queue.clone(), self.gate.clone())
+    }
+    /// Instead of linking the result, `f` is called with it on the runner's thread once the
+    /// command is executed. If `f` panics, the runner keeps going and hands the panic to
+    /// [`RunnerConfig::on_abandoned`](crate::RunnerConfig::on_abandoned).
+    ///
+    /// # Errors
+    /// Fails if every runner has stopped.
+    pub fn send_with_callback(
+        &self,
+        cmd: Cmd,
+        f: impl FnOnce(CmdRst<Cmd>) + Send + 'static,
+    ) -> Result<(), SendError<Cmd>> {
+        let reply = Reply::Callback(Box::new(f));
+        self.send_queued(QueuedCommand { cmd, reply })
+    }
+    /// # Errors
+    /// Fails if every runner has stopped.
+    fn send_queued(&self, msg: QueuedCommand<Cmd>) -> Result<(), SendError<Cmd>> {
+        self.cmd_queue
+            .send_t(msg)
+            .map_err(|e| SendError(e.into().0.cmd))
     }
 }
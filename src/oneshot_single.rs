@@ -1,69 +1,127 @@
-use std::any::Any;
-use std::fmt;
-use std::sync::mpsc;
-use std::thread::JoinHandle;
+use crate::sync::thread::JoinHandle;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::oneshot::{ExternalCommandLink, OneShotRunner, OneshotEventLoopError, QueuedCommand};
-use crate::{Command, CommandRunner};
-type SR<Cmd> = mpsc::Receiver<QueuedCommand<Cmd>>;
+use crate::channel::{Channel, Std};
+use crate::close::{self, send_until};
+use crate::error::{CloseError, EventLoopError, SendError};
+use crate::oneshot::{ExternalCommandLink, OneShotRunner, QueuedCommand, Reply};
+use crate::runtime::Runners;
+use crate::submit::{Gate, LinkSubmitter};
+use crate::{ChanSend, CloseTimeout, CmdRst, Command, CommandRunner, RunnerConfig, TimeoutReport};
+type SR<Cmd, Ch> = <Ch as Channel>::Receiver<QueuedCommand<Cmd>>;
+type SingleRunner<Cmd, Ch> = OneShotRunner<Cmd, SR<Cmd, Ch>>;
+type Worker<Cmd, Ch> = JoinHandle<Result<SingleRunner<Cmd, Ch>, EventLoopError<CmdRst<Cmd>>>>;
 
-pub struct OneShotAPI<Cmd>
+/// [`OneShotChanAPI`] with it's commands queued in [`std`]'s channel
+pub type OneShotAPI<Cmd> = OneShotChanAPI<Cmd, Std>;
+
+/// Commands are queued in a `Ch` [channel](crate::channel).
+pub struct OneShotChanAPI<Cmd, Ch>
 where
     Cmd: Command,
+    Ch: Channel,
 {
-    cmd_queue: mpsc::Sender<QueuedCommand<Cmd>>,
-    thread: JoinHandle<Result<OneShotRunner<Cmd, SR<Cmd>>, OneshotEventLoopError<Cmd>>>,
+    cmd_queue: Ch::Sender<QueuedCommand<Cmd>>,
+    thread: Worker<Cmd, Ch>,
+    gate: Arc<Gate>,
 }
 
-#[derive(Debug)]
-pub enum OneShotCloseError<Cmd>
+impl<Cmd, Ch> CommandRunner for OneShotChanAPI<Cmd, Ch>
 where
     Cmd: Command,
+    Ch: Channel,
 {
-    Send(QueuedCommand<Cmd>),
-    Join(Box<dyn Any + Send>),
-    Worker(OneshotEventLoopError<Cmd>),
+    type Cmd = Cmd;
+    type SendAck = Result<ExternalCommandLink<Cmd>, SendError<Cmd>>;
+    type CloseResult = Result<SingleRunner<Cmd, Ch>, CloseError<Cmd, CmdRst<Cmd>>>;
+    unsafe fn with_config(config: &RunnerConfig) -> Self {
+        let (tx, rx) = Ch::channel();
+        let thread = OneShotRunner::spawn(config, "supera-oneshot", 0, rx);
+        OneShotChanAPI {
+            cmd_queue: tx,
+            thread,
+            gate: Arc::default(),
+        }
+    }
+    fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
+        let (reply, rx) = Reply::link();
+        self.send_queued(QueuedCommand { cmd, reply })?;
+        Ok(rx)
+    }
+    fn close_with(self, mut c: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
+        self.gate.close();
+        // Sending only fails once the runner stopped, which joining it reports
+        let _ = self.send(c.get());
+        Ok(self.thread.join().map_err(EventLoopError::Panic)??)
+    }
 }
 
-impl<Cmd: Command + fmt::Debug> fmt::Display for OneShotCloseError<Cmd> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Join(e) => write!(f, "Failed to join thread, {e:?}"),
-            Self::Send(cmd) => write!(f, "Failed to send command {cmd:?}"),
-            Self::Worker(e) => write!(f, "Worker failed with: {e}"),
-        }
+impl<Cmd, Ch> CloseTimeout for OneShotChanAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    type Runner = SingleRunner<Cmd, Ch>;
+    type Rst = CmdRst<Cmd>;
+    fn close_timeout_with(
+        self,
+        mut c: impl crate::StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        let (reply, _) = Reply::link();
+        let stop = QueuedCommand {
+            cmd: c.get(),
+            reply,
+        };
+        // If it's not sent, the runner either stopped or is stuck, which joining it reports
+        let _ = send_until(&self.cmd_queue, stop, deadline);
+        TimeoutReport::join_until([self.thread], deadline)
     }
 }
 
-impl<Cmd: fmt::Debug + Command> std::error::Error for OneShotCloseError<Cmd> {}
+impl<Cmd, Ch> Runners for OneShotChanAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    const RUNNERS: usize = 1;
+}
 
-impl<Cmd> CommandRunner for OneShotAPI<Cmd>
+impl<Cmd, Ch> OneShotChanAPI<Cmd, Ch>
 where
     Cmd: Command,
-    <Cmd as Command>::Result: fmt::Debug,
+    Ch: Channel,
 {
-    type Cmd = Cmd;
-    type SendAck = Result<ExternalCommandLink<Cmd>, QueuedCommand<Cmd>>;
-    type CloseResult = Result<OneShotRunner<Cmd, SR<Cmd>>, OneShotCloseError<Cmd>>;
-    unsafe fn new() -> Self {
-        let (tx, rx) = std::sync::mpsc::channel();
-        let thread = OneShotRunner::spawn(rx);
-        OneShotAPI {
-            cmd_queue: tx,
-            thread,
-        }
+    /// Handle that can send commands from other threads, until the manager is closed
+    #[must_use]
+    pub fn submitter(&self) -> LinkSubmitter<Cmd, Ch::Sender<QueuedCommand<Cmd>>>
+    where
+        Ch::Sender<QueuedCommand<Cmd>>: Clone,
+    {
+        LinkSubmitter::new(self.cmd_queue.clone(), self.gate.clone())
     }
-    fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
-        let (tx, rx) = oneshot::channel();
-        let msg = QueuedCommand { cmd, chan: tx };
-        self.cmd_queue.send(msg).map_err(|e| e.0)?;
-        Ok(rx)
+    /// Instead of linking the result, `f` is called with it on the runner's thread once the
+    /// command is executed. If `f` panics, the runner keeps going and hands the panic to
+    /// [`RunnerConfig::on_abandoned`](crate::RunnerConfig::on_abandoned).
+    ///
+    /// # Errors
+    /// Fails if the runner has stopped.
+    pub fn send_with_callback(
+        &self,
+        cmd: Cmd,
+        f: impl FnOnce(CmdRst<Cmd>) + Send + 'static,
+    ) -> Result<(), SendError<Cmd>> {
+        let reply = Reply::Callback(Box::new(f));
+        self.send_queued(QueuedCommand { cmd, reply })
     }
-    fn close_with(self, mut c: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
-        self.send(c.get()).map_err(OneShotCloseError::Send)?;
-        self.thread
-            .join()
-            .map_err(OneShotCloseError::Join)?
-            .map_err(OneShotCloseError::Worker)
+    /// # Errors
+    /// Fails if the runner has stopped.
+    fn send_queued(&self, msg: QueuedCommand<Cmd>) -> Result<(), SendError<Cmd>> {
+        self.cmd_queue
+            .send_t(msg)
+            .map_err(|e| SendError(e.into().0.cmd))
     }
 }
@@ -0,0 +1,282 @@
+use crate::error::{EventLoopError, SendError};
+use crate::sync::thread::JoinHandle;
+use crate::{ActionResult, ChanRecv, CmdRst, Command, ResultQueue, RunnerConfig};
+use crossbeam_channel as mpmc;
+use std::any::Any;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A stage's runners only fail configuring their thread or panicking, the results they can't
+/// deliver are [abandoned](RunnerConfig::on_abandoned)
+type StageError = EventLoopError<Box<dyn Any + Send>>;
+type StageWorker = JoinHandle<Result<(), StageError>>;
+type StageSpawner = Box<dyn FnOnce(usize) -> Vec<StageWorker>>;
+
+/// Receives the previous stage's outputs as the commands of a stage
+struct Mapped<Out, F> {
+    recv: mpmc::Receiver<Out>,
+    map: Arc<F>,
+}
+
+impl<Out, C, F> ChanRecv<C> for Mapped<Out, F>
+where
+    F: Fn(Out) -> C,
+{
+    type Err = mpmc::RecvError;
+    fn recv_t(&self) -> Result<C, Self::Err> {
+        self.recv.recv().map(&*self.map)
+    }
+    fn try_recv_t(&self) -> Result<C, mpmc::TryRecvError> {
+        self.recv.try_recv().map(&*self.map)
+    }
+    fn recv_timeout_t(&self, timeout: Duration) -> Result<C, mpmc::RecvTimeoutError> {
+        self.recv.recv_timeout(timeout).map(&*self.map)
+    }
+}
+
+/// Describes the stages of a [`Pipeline`], where `In` is what's sent to the pipeline and `Out`
+/// what comes out of the last stage
+///
+/// Each stage has it's own runners and command type, the results of a stage are converted into
+/// the commands of the next one. Stages are connected by bounded queues, so a slow stage blocks
+/// the ones before it, and eventually [`Pipeline::send`].
+pub struct PipelineBuilder<In, Out> {
+    input: mpmc::Sender<In>,
+    output: mpmc::Receiver<Out>,
+    stages: Vec<StageSpawner>,
+}
+
+impl<T> PipelineBuilder<T, T>
+where
+    T: Send + 'static,
+{
+    /// Pipeline without any stages, which queues up to `capacity` inputs
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        let (input, output) = mpmc::bounded(capacity);
+        Self {
+            input,
+            output,
+            stages: Vec::new(),
+        }
+    }
+}
+
+impl<In, Out> PipelineBuilder<In, Out>
+where
+    In: Send + 'static,
+    Out: Send + 'static,
+{
+    /// Adds a stage with `workers` runners, each output of the previous stage is converted to a
+    /// command with `map`. Up to `capacity` results of this stage are queued.
+    #[must_use]
+    pub fn stage<C>(
+        self,
+        workers: usize,
+        capacity: usize,
+        map: impl Fn(Out) -> C + Send + Sync + 'static,
+    ) -> PipelineBuilder<In, CmdRst<C>>
+    where
+        C: Command,
+        CmdRst<C>: 'static,
+    {
+        self.stage_with_config(workers, capacity, &RunnerConfig::default(), map)
+    }
+
+    /// Same as [`PipelineBuilder::stage`], but the stage's runners are spawned with `config`
+    ///
+    /// Their threads are named `supera-p{stage}-{index}` unless `config` sets a prefix.
+    #[must_use]
+    pub fn stage_with_config<C>(
+        self,
+        workers: usize,
+        capacity: usize,
+        config: &RunnerConfig,
+        map: impl Fn(Out) -> C + Send + Sync + 'static,
+    ) -> PipelineBuilder<In, CmdRst<C>>
+    where
+        C: Command,
+        CmdRst<C>: 'static,
+    {
+        let (tx, rx) = mpmc::bounded(capacity);
+        let recv = self.output;
+        let map = Arc::new(map);
+        let config = config.clone();
+        let mut stages = self.stages;
+        stages.push(Box::new(move |stage| {
+            let name = format!("supera-p{stage}");
+            (0..workers)
+                .map(|i| {
+                    let input = Mapped {
+                        recv: recv.clone(),
+                        map: map.clone(),
+                    };
+                    let send = tx.clone();
+                    let mut abandoned = config.abandoned();
+                    let mut dispatch = config.dispatch();
+                    let mut deliver = move |res| {
+                        if let Err(e) = send.send(res) {
+                            abandoned.give(e.0);
+                        }
+                    };
+                    config.spawn(&name, i, move || {
+                        // Disconnected once the previous stage is done
+                        while let Ok((cmd, permit)) = dispatch.next(&input, C::class) {
+                            let r = cmd.execute();
+                            drop(permit);
+                            let ActionResult::Normal(res) = r else { break };
+                            deliver(res);
+                        }
+                        // Commands put off by their class's limits are still executed
+                        while let Some((cmd, permit)) = dispatch.deferred() {
+                            let r = cmd.execute();
+                            drop(permit);
+                            if let ActionResult::Normal(res) = r {
+                                deliver(res);
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+                .collect()
+        }));
+        PipelineBuilder {
+            input: self.input,
+            output: rx,
+            stages,
+        }
+    }
+
+    /// # Safety
+    /// Since this starts other threads, it's only safe to call this if [`Pipeline::close`] is
+    /// called.
+    ///
+    /// [`PipelineBuilder::scope`] always closes the pipeline.
+    #[must_use]
+    pub unsafe fn build(self) -> Pipeline<In, Out> {
+        let stages = self
+            .stages
+            .into_iter()
+            .enumerate()
+            .map(|(i, spawn)| spawn(i))
+            .collect();
+        Pipeline {
+            input: self.input,
+            output: self.output,
+            stages,
+        }
+    }
+
+    /// No need to remember to .close the pipeline if you use scope
+    pub fn scope(self, f: impl FnOnce(&Pipeline<In, Out>)) -> Drained<Out> {
+        let pipeline = unsafe { self.build() };
+        f(&pipeline);
+        pipeline.close()
+    }
+}
+
+/// Running stages built by a [`PipelineBuilder`]
+pub struct Pipeline<In, Out> {
+    input: mpmc::Sender<In>,
+    output: mpmc::Receiver<Out>,
+    stages: Vec<Vec<StageWorker>>,
+}
+
+/// What was left in a [`Pipeline`] once it closed
+#[derive(Debug)]
+pub struct Drained<Out> {
+    /// Outputs that were not received before closing
+    pub outputs: Vec<Out>,
+    /// How each runner of each stage stopped
+    pub stages: Vec<Vec<Result<(), StageError>>>,
+}
+
+impl<In, Out> Pipeline<In, Out> {
+    /// Blocks while the first stage's queue is full
+    ///
+    /// # Errors
+    /// Fails if every runner of the first stage has stopped.
+    pub fn send(&self, input: In) -> Result<(), SendError<In>> {
+        Ok(self.input.send(input)?)
+    }
+    /// # Errors
+    /// Fails if the first stage's queue is full or every one of it's runners has stopped.
+    pub fn try_send(&self, input: In) -> Result<(), mpmc::TrySendError<In>> {
+        self.input.try_send(input)
+    }
+    /// # Errors
+    /// Fails if every runner of the last stage has stopped.
+    pub fn recv(&self) -> Result<Out, mpmc::RecvError> {
+        self.output.recv()
+    }
+    /// # Errors
+    /// Fails if no output is available.
+    pub fn try_recv(&self) -> Result<Out, mpmc::TryRecvError> {
+        self.output.try_recv()
+    }
+    /// See [`ResultQueue::recv_timeout`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives within `timeout`, or if every runner has stopped.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Out, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_timeout(self, timeout)
+    }
+    /// See [`ResultQueue::recv_deadline`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives before `deadline`, or if every runner has stopped.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<Out, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_deadline(self, deadline)
+    }
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    #[must_use]
+    pub fn iter(&self) -> mpmc::Iter<'_, Out> {
+        ResultQueue::iter(self)
+    }
+    /// Results that are available, without blocking
+    #[must_use]
+    pub fn try_iter(&self) -> mpmc::TryIter<'_, Out> {
+        ResultQueue::try_iter(self)
+    }
+    /// Collects every result that's available right now
+    #[must_use]
+    pub fn drain(&self) -> Vec<Out> {
+        ResultQueue::drain(self)
+    }
+
+    /// Stops accepting inputs and waits for every stage, in order, to finish the commands it
+    /// has queued.
+    #[must_use]
+    pub fn close(self) -> Drained<Out> {
+        drop(self.input);
+        // Only disconnected once every stage is done
+        let outputs = self.output.iter().collect();
+        let stages = self
+            .stages
+            .into_iter()
+            .map(|workers| {
+                workers
+                    .into_iter()
+                    .map(|w| w.join().map_err(EventLoopError::Panic)?)
+                    .collect()
+            })
+            .collect();
+        Drained { outputs, stages }
+    }
+}
+
+impl<In, Out> ResultQueue for Pipeline<In, Out> {
+    type Item = Out;
+    fn results(&self) -> &mpmc::Receiver<Self::Item> {
+        &self.output
+    }
+}
+
+impl<'a, In, Out> IntoIterator for &'a Pipeline<In, Out> {
+    type Item = Out;
+    type IntoIter = mpmc::Iter<'a, Out>;
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
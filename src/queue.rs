@@ -1,7 +1,8 @@
-use crate::{ActionResult, ChanRecv, ChanSend, CmdRst, Command};
-use std::fmt;
+use crate::config::Abandoned;
+use crate::error::EventLoopError;
+use crate::sync::thread::JoinHandle;
+use crate::{ActionResult, ChanRecv, ChanSend, CmdRst, Command, RunnerConfig, Stopped};
 use std::marker::PhantomData;
-use std::thread::JoinHandle;
 
 /// Runner that sends responses to a queue
 pub struct QueueRunner<Cmd, R, S>
@@ -13,42 +14,44 @@ where
     pub(crate) d: PhantomData<Cmd>,
     pub(crate) recv_cmd: R,
     pub(crate) send_res: S,
+    pub(crate) abandoned: Abandoned,
 }
 
-#[derive(Debug)]
-pub enum QueueEventLoopError {
-    SendErr,
-    RecvErr,
-    ThreadPanic(Box<dyn std::any::Any + Send>),
-}
-
-impl fmt::Display for QueueEventLoopError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::RecvErr => write!(f, "Failed to recieve"),
-            Self::SendErr => write!(f, "Failed to write"),
-            Self::ThreadPanic(..) => write!(f, "Worker panicked"),
-        }
-    }
-}
-
-impl std::error::Error for QueueEventLoopError {}
-
 impl<Cmd, S, R> QueueRunner<Cmd, R, S>
 where
     Cmd: Command,
     R: ChanRecv<Cmd>,
     S: ChanSend<CmdRst<Cmd>>,
 {
-    pub(crate) fn get(&self) -> Result<Cmd, R::Err> {
-        self.recv_cmd.recv_t()
-    }
+    /// # Errors
+    /// Fails if the result channel is closed
     pub(crate) fn send(&self, res: CmdRst<Cmd>) -> Result<(), S::Err> {
         self.send_res.send_t(res)
     }
     pub(crate) fn exec(cmd: Cmd) -> ActionResult<CmdRst<Cmd>> {
         cmd.execute()
     }
+    fn deliver(&mut self, res: CmdRst<Cmd>) {
+        if let Err(e) = self.send(res) {
+            self.abandoned.give(e.into().0);
+        }
+    }
+    /// How many results were dropped, because the result queue was
+    #[must_use]
+    pub fn abandoned(&self) -> u64 {
+        self.abandoned.count()
+    }
+}
+
+impl<Cmd, R, S, Rst> Stopped<Rst> for QueueRunner<Cmd, R, S>
+where
+    Cmd: Command,
+    R: ChanRecv<Cmd>,
+    S: ChanSend<CmdRst<Cmd>>,
+{
+    fn failures(self) -> Vec<EventLoopError<Rst>> {
+        Vec::new()
+    }
 }
 
 impl<Cmd, R, S> QueueRunner<Cmd, R, S>
@@ -56,23 +59,46 @@ where
     Cmd: Command,
     R: ChanRecv<Cmd> + Send + 'static,
     S: ChanSend<CmdRst<Cmd>> + Send + 'static,
-    <R as ChanRecv<Cmd>>::Err: std::fmt::Debug,
-    <S as ChanSend<Cmd::Result>>::Err: std::fmt::Debug,
 {
-    /// # Panics
-    /// The default runners panic if the channels they're bound to are dropped.
-    pub(crate) fn spawn(recv_cmd: R, send_res: S) -> JoinHandle<Result<Self, QueueEventLoopError>> {
-        std::thread::spawn(|| {
-            let runner = Self {
+    /// A dropped result queue doesn't stop the runner, the results are handed to
+    /// [`RunnerConfig::on_abandoned`] instead.
+    pub(crate) fn spawn(
+        config: &RunnerConfig,
+        name: &str,
+        index: usize,
+        recv_cmd: R,
+        send_res: S,
+    ) -> JoinHandle<Result<Self, EventLoopError<CmdRst<Cmd>>>> {
+        let abandoned = config.abandoned();
+        let mut dispatch = config.dispatch();
+        let tally = config.tally();
+        config.spawn(name, index, move || {
+            let mut runner = Self {
                 recv_cmd,
                 send_res,
+                abandoned,
                 d: PhantomData,
             };
             loop {
-                let cmd = runner.get().map_err(|_| QueueEventLoopError::RecvErr)?;
+                let (cmd, permit) = dispatch
+                    .next(&runner.recv_cmd, Cmd::class)
+                    .map_err(|_| EventLoopError::Recv)?;
+                tally.received();
                 let r = Self::exec(cmd);
+                drop(permit);
                 let ActionResult::Normal(res) = r else { break };
-                runner.send(res).map_err(|_| QueueEventLoopError::SendErr)?;
+                tally.executed();
+                runner.deliver(res);
+            }
+            // Commands put off by their class's limits are still executed before stopping
+            while let Some((cmd, permit)) = dispatch.deferred() {
+                tally.received();
+                let r = Self::exec(cmd);
+                drop(permit);
+                if let ActionResult::Normal(res) = r {
+                    tally.executed();
+                    runner.deliver(res);
+                }
             }
             Ok(runner)
         })
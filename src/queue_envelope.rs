@@ -0,0 +1,311 @@
+use crate::channel::{Channel, Crossbeam, SharedChannel};
+use crate::close::{self, send_until};
+use crate::error::{EventLoopError, SendError};
+use crate::queue::QueueRunner;
+use crate::runtime::Runners;
+use crate::submit::{EnvelopeSubmitter, Gate};
+use crate::sync::thread::JoinHandle;
+use crate::{
+    ActionResult, ChanRecv, ChanSend, CloseReport, CloseTimeout, CmdRst, Command, CommandRunner,
+    ResultQueue, RunnerConfig, TimeoutReport,
+};
+use crossbeam_channel as mpmc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+type EnvelopeError<Cmd> = EventLoopError<Envelope<CmdRst<Cmd>>>;
+type Worker<Cmd, Ch> = JoinHandle<Result<EnvelopeRunner<Cmd, Ch>, EnvelopeError<Cmd>>>;
+
+/// A [`QueueRunner`] that sends responses, wrapped in an [`Envelope`], to a queue
+pub type EnvelopeRunner<Cmd, Ch = Crossbeam> =
+    QueueRunner<Tagged<Cmd>, <Ch as Channel>::Receiver<Tagged<Cmd>>, WorkerSender<CmdRst<Cmd>>>;
+
+/// Identifies a command sent to an [`EnvelopePoolAPI`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommandId(pub u64);
+
+/// Identifies which runner of an [`EnvelopePoolAPI`] executed a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WorkerId(pub usize);
+
+/// A command's result, along with where it came from
+#[derive(Debug)]
+pub struct Envelope<Rst> {
+    /// Same id returned by [`EnvelopePoolAPI::send`]
+    pub id: CommandId,
+    pub worker: WorkerId,
+    /// How long the command took to execute
+    pub elapsed: Duration,
+    pub result: Rst,
+}
+
+impl<Rst> Envelope<Rst> {
+    pub fn into_parts(self) -> (CommandId, WorkerId, Duration, Rst) {
+        (self.id, self.worker, self.elapsed, self.result)
+    }
+}
+
+/// A command queued in an [`EnvelopePoolAPI`], along with it's id
+///
+/// Executing it times the command, it's result is stamped with the runner by [`WorkerSender`].
+#[derive(Debug)]
+pub struct Tagged<Cmd> {
+    id: CommandId,
+    cmd: Cmd,
+}
+
+impl<Cmd> Tagged<Cmd> {
+    pub(crate) fn id(&self) -> CommandId {
+        self.id
+    }
+}
+
+impl<Cmd: Command> Command for Tagged<Cmd> {
+    type Result = Envelope<CmdRst<Cmd>>;
+    fn execute(self) -> ActionResult<Self::Result> {
+        let start = Instant::now();
+        let ActionResult::Normal(result) = self.cmd.execute() else {
+            return ActionResult::Stop;
+        };
+        ActionResult::Normal(Envelope {
+            id: self.id,
+            worker: WorkerId(0),
+            elapsed: start.elapsed(),
+            result,
+        })
+    }
+    fn class(&self) -> Option<&'static str> {
+        self.cmd.class()
+    }
+}
+
+/// Result queue of an [`EnvelopePoolAPI`]'s runner, stamps every envelope with the runner's id
+pub struct WorkerSender<Rst> {
+    worker: WorkerId,
+    chan: mpmc::Sender<Envelope<Rst>>,
+}
+
+impl<Rst> ChanSend<Envelope<Rst>> for WorkerSender<Rst> {
+    type Err = mpmc::SendError<Envelope<Rst>>;
+    fn send_t(&self, mut envelope: Envelope<Rst>) -> Result<(), Self::Err> {
+        envelope.worker = self.worker;
+        self.chan.send(envelope)
+    }
+    fn try_send_t(
+        &self,
+        mut envelope: Envelope<Rst>,
+    ) -> Result<(), mpmc::TrySendError<Envelope<Rst>>> {
+        envelope.worker = self.worker;
+        self.chan.try_send(envelope)
+    }
+}
+
+impl<Cmd, R> QueueRunner<Tagged<Cmd>, R, WorkerSender<CmdRst<Cmd>>>
+where
+    Cmd: Command,
+    R: ChanRecv<Tagged<Cmd>>,
+{
+    #[must_use]
+    pub fn worker(&self) -> WorkerId {
+        self.send_res.worker
+    }
+}
+
+/// Same as [`PoolQueueAPI`](crate::queue_pool::PoolQueueAPI), but every result is tagged with
+/// the id of the command that produced it, the runner that executed it and how long it took.
+///
+/// Commands are queued in a `Ch` [channel](crate::channel), [`crossbeam_channel`]'s by default.
+pub struct EnvelopePoolAPI<Cmd, const N: usize, Ch = Crossbeam>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    next_id: Arc<AtomicU64>,
+    send_cmd: Ch::Sender<Tagged<Cmd>>,
+    recv_res: mpmc::Receiver<Envelope<CmdRst<Cmd>>>,
+    runners: [Worker<Cmd, Ch>; N],
+    gate: Arc<Gate>,
+}
+
+impl<Cmd, const N: usize, Ch> CommandRunner for EnvelopePoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Cmd = Cmd;
+    type SendAck = Result<CommandId, SendError<Cmd>>;
+    type CloseResult = CloseReport<Cmd, EnvelopeRunner<Cmd, Ch>, Envelope<CmdRst<Cmd>>>;
+    unsafe fn with_config(config: &RunnerConfig) -> Self {
+        let (tx_cmd, rx_cmd) = Ch::channel();
+        let (tx_res, rx_res) = mpmc::unbounded();
+        let runners = std::array::from_fn(|i| {
+            let send_res = WorkerSender {
+                worker: WorkerId(i),
+                chan: tx_res.clone(),
+            };
+            QueueRunner::spawn(config, "supera-envpool", i, Ch::share(&rx_cmd), send_res)
+        });
+        Self {
+            next_id: Arc::default(),
+            send_cmd: tx_cmd,
+            recv_res: rx_res,
+            runners,
+            gate: Arc::default(),
+        }
+    }
+    fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
+        let tagged = tag(&self.next_id, cmd);
+        let id = tagged.id;
+        self.send_cmd
+            .send_t(tagged)
+            .map_err(|e| SendError(untag(e.into().0)))?;
+        Ok(id)
+    }
+    fn close_with(self, mut s: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
+        self.gate.close();
+        for _ in 0..N {
+            // Sending only fails once every runner stopped, which joining them reports
+            if self.send(s.get()).is_err() {
+                break;
+            }
+        }
+        CloseReport::join(self.runners)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> CloseTimeout for EnvelopePoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Runner = EnvelopeRunner<Cmd, Ch>;
+    type Rst = Envelope<CmdRst<Cmd>>;
+    fn close_timeout_with(
+        self,
+        mut s: impl crate::StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        for _ in 0..N {
+            let stop = tag(&self.next_id, s.get());
+            // Not sent in time, or every runner stopped, which joining them reports
+            if send_until(&self.send_cmd, stop, deadline).is_err() {
+                break;
+            }
+        }
+        TimeoutReport::join_until(self.runners, deadline)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> Runners for EnvelopePoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    const RUNNERS: usize = N;
+}
+
+impl<Cmd, const N: usize, Ch> EnvelopePoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    /// # Errors
+    /// An error would occour if every [runner](EnvelopeRunner) was closed but the
+    /// [api](EnvelopePoolAPI) was not dropped.
+    pub fn recv(&self) -> Result<Envelope<CmdRst<Cmd>>, mpmc::RecvError> {
+        self.recv_res.recv()
+    }
+    /// # Errors
+    /// An error would occour if every [runner](EnvelopeRunner) was closed but the
+    /// [api](EnvelopePoolAPI) was not dropped.
+    pub fn try_recv(&self) -> Result<Envelope<CmdRst<Cmd>>, mpmc::TryRecvError> {
+        self.recv_res.try_recv()
+    }
+    /// See [`ResultQueue::recv_timeout`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives within `timeout`, or if every runner has stopped.
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Envelope<CmdRst<Cmd>>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_timeout(self, timeout)
+    }
+    /// See [`ResultQueue::recv_deadline`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives before `deadline`, or if every runner has stopped.
+    pub fn recv_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<Envelope<CmdRst<Cmd>>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_deadline(self, deadline)
+    }
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    #[must_use]
+    pub fn iter(&self) -> mpmc::Iter<'_, Envelope<CmdRst<Cmd>>> {
+        ResultQueue::iter(self)
+    }
+    /// Results that are available, without blocking
+    #[must_use]
+    pub fn try_iter(&self) -> mpmc::TryIter<'_, Envelope<CmdRst<Cmd>>> {
+        ResultQueue::try_iter(self)
+    }
+    /// Collects every result that's available right now
+    #[must_use]
+    pub fn drain(&self) -> Vec<Envelope<CmdRst<Cmd>>> {
+        ResultQueue::drain(self)
+    }
+    /// Handle that can send commands from other threads, until the manager is closed
+    ///
+    /// Ids are shared with the manager, so they stay unique across every submitter.
+    #[must_use]
+    pub fn submitter(&self) -> EnvelopeSubmitter<Cmd, Ch::Sender<Tagged<Cmd>>>
+    where
+        Ch::Sender<Tagged<Cmd>>: Clone,
+    {
+        EnvelopeSubmitter::new(
+            self.send_cmd.clone(),
+            self.gate.clone(),
+            self.next_id.clone(),
+        )
+    }
+}
+
+/// Tags `cmd` with the next id
+pub(crate) fn tag<Cmd>(next_id: &AtomicU64, cmd: Cmd) -> Tagged<Cmd> {
+    let id = CommandId(next_id.fetch_add(1, Ordering::Relaxed));
+    Tagged { id, cmd }
+}
+
+/// Gives the command back from a tagged one
+pub(crate) fn untag<Cmd>(tagged: Tagged<Cmd>) -> Cmd {
+    tagged.cmd
+}
+
+impl<Cmd, const N: usize, Ch> ResultQueue for EnvelopePoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Item = Envelope<CmdRst<Cmd>>;
+    fn results(&self) -> &mpmc::Receiver<Self::Item> {
+        &self.recv_res
+    }
+}
+
+impl<'a, Cmd, const N: usize, Ch> IntoIterator for &'a EnvelopePoolAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Item = Envelope<CmdRst<Cmd>>;
+    type IntoIter = mpmc::Iter<'a, Envelope<CmdRst<Cmd>>>;
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
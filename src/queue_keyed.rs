@@ -0,0 +1,322 @@
+use crate::channel::{Channel, Std};
+use crate::close::{self, send_until};
+use crate::error::{EventLoopError, SendError};
+use crate::queue::QueueRunner;
+use crate::submit::{Gate, KeyedSubmitter};
+use crate::sync::thread::JoinHandle;
+use crate::sync::{RandomState, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::{
+    ChanSend, CloseReport, CmdRst, Command, ResultQueue, RunnerConfig, SimpleCloser, SimpleStop,
+    StopRunner, TimeoutReport,
+};
+use crossbeam_channel as mpmc;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
+
+type SS<Cmd> = mpmc::Sender<CmdRst<Cmd>>;
+type KeyedRunner<Cmd, Ch> = QueueRunner<Cmd, <Ch as Channel>::Receiver<Cmd>, SS<Cmd>>;
+type Stopped<Cmd, Ch> = Result<KeyedRunner<Cmd, Ch>, EventLoopError<CmdRst<Cmd>>>;
+type KeyedReport<Cmd, Ch> = CloseReport<Cmd, KeyedRunner<Cmd, Ch>, CmdRst<Cmd>>;
+
+/// Command queues of a [`KeyedPoolAPI`]'s runners, shared with it's submitters
+///
+/// Resizing the pool holds the queues until the new runners are spawned, so a command is
+/// never sent to a runner that's stopping.
+pub(crate) struct Shards<S> {
+    hasher: RandomState,
+    senders: RwLock<Vec<S>>,
+}
+
+impl<S> Shards<S> {
+    /// Index of the queue out of `len` that commands sent with `key` go to
+    pub(crate) fn index<K: Hash + ?Sized>(&self, key: &K, len: usize) -> usize {
+        let hash = self.hasher.hash_one(key);
+        // The remainder is always smaller than the amount of queues, so it fits a usize
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (hash % len as u64) as usize;
+        index
+    }
+
+    pub(crate) fn senders(&self) -> RwLockReadGuard<'_, Vec<S>> {
+        self.senders.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn senders_mut(&self) -> RwLockWriteGuard<'_, Vec<S>> {
+        self.senders.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// API of [`QueueRunner`] for managing multiple runners, each with it's own queue
+///
+/// Every command is sent along with a key, commands with the same key are always executed by
+/// the same runner, in the order they were sent, even under the config's limits. Commands with
+/// different keys may run in parallel.
+///
+/// Only the runners hold the result queue's sender, so it disconnects once all of them stopped.
+///
+/// Each runner's commands are queued in a `Ch` [channel](crate::channel), [`std`]'s by default.
+pub struct KeyedPoolAPI<Cmd, Ch = Std>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    config: RunnerConfig,
+    shards: Arc<Shards<Ch::Sender<Cmd>>>,
+    recv_res: mpmc::Receiver<CmdRst<Cmd>>,
+    threads: Vec<JoinHandle<Stopped<Cmd, Ch>>>,
+    gate: Arc<Gate>,
+}
+
+impl<Cmd, Ch> KeyedPoolAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    /// # Safety
+    /// See [`CommandRunner::new`](crate::CommandRunner::new)
+    ///
+    /// # Panics
+    /// Panics if `workers` is zero.
+    #[must_use]
+    pub unsafe fn new(workers: usize) -> Self {
+        unsafe { Self::with_config(workers, RunnerConfig::default()) }
+    }
+
+    /// # Safety
+    /// See [`CommandRunner::new`](crate::CommandRunner::new)
+    ///
+    /// # Panics
+    /// Panics if `workers` is zero.
+    #[must_use]
+    pub unsafe fn with_config(workers: usize, config: RunnerConfig) -> Self {
+        let (send_res, recv_res) = mpmc::unbounded();
+        let mut pool = Self {
+            config: config.in_order(),
+            shards: Arc::new(Shards {
+                hasher: RandomState::default(),
+                senders: RwLock::new(Vec::new()),
+            }),
+            recv_res,
+            threads: Vec::new(),
+            gate: Arc::default(),
+        };
+        let senders = pool.spawn_workers(workers, &send_res);
+        *pool.shards.senders_mut() = senders;
+        pool
+    }
+
+    /// Spawns `count` runners, returning their queues
+    ///
+    /// # Panics
+    /// Panics if `count` is zero.
+    fn spawn_workers(&mut self, count: usize, send_res: &SS<Cmd>) -> Vec<Ch::Sender<Cmd>> {
+        assert!(count > 0, "a keyed pool needs at least one worker");
+        let (senders, threads) = (0..count)
+            .map(|i| {
+                let (send_cmd, recv_cmd) = Ch::channel();
+                let thread =
+                    QueueRunner::spawn(&self.config, "supera-keyed", i, recv_cmd, send_res.clone());
+                (send_cmd, thread)
+            })
+            .unzip();
+        self.threads = threads;
+        senders
+    }
+
+    /// Stops every worker, after they executed all their pending commands
+    fn stop_workers(
+        &mut self,
+        senders: &[Ch::Sender<Cmd>],
+        s: &mut impl StopRunner<Cmd>,
+    ) -> KeyedReport<Cmd, Ch> {
+        for sender in senders {
+            // A worker can only refuse the stop command if it already stopped, which joining
+            // it will report
+            let _ = sender.send_t(s.get());
+        }
+        CloseReport::join(std::mem::take(&mut self.threads))
+    }
+
+    /// Sender of the result queue for new runners, taken from one that stopped cleanly
+    ///
+    /// If none did, every sender is gone, so the queue is replaced by a new one that gets the
+    /// results still pending first.
+    fn result_sender(&mut self, old: &KeyedReport<Cmd, Ch>) -> SS<Cmd> {
+        if let Some((_, runner)) = old.clean().next() {
+            return runner.send_res.clone();
+        }
+        let (send_res, recv_res) = mpmc::unbounded();
+        for res in self.recv_res.try_iter() {
+            // The new receiver is alive, so it's never refused
+            let _ = send_res.send(res);
+        }
+        self.recv_res = recv_res;
+        send_res
+    }
+
+    /// Amount of runners in the pool
+    #[must_use]
+    pub fn workers(&self) -> usize {
+        self.threads.len()
+    }
+
+    /// Index of the runner that executes the commands sent with `key`
+    pub fn worker_for<K: Hash + ?Sized>(&self, key: &K) -> usize {
+        self.shards.index(key, self.threads.len())
+    }
+
+    /// # Errors
+    /// Fails if the runner responsible for `key` has stopped.
+    pub fn send<K: Hash + ?Sized>(&self, key: &K, cmd: Cmd) -> Result<(), SendError<Cmd>> {
+        self.shards.senders()[self.worker_for(key)]
+            .send_t(cmd)
+            .map_err(Into::into)
+    }
+
+    /// Handle that can send commands from other threads, until the manager is closed
+    ///
+    /// It sends each key to the same runner as the manager, also once the pool was resized.
+    #[must_use]
+    pub fn submitter(&self) -> KeyedSubmitter<Cmd, Ch::Sender<Cmd>> {
+        KeyedSubmitter::new(self.shards.clone(), self.gate.clone())
+    }
+
+    /// Changes the amount of runners, rebalancing the keys between them.
+    ///
+    /// Every runner executes it's pending commands and is stopped before the new runners are
+    /// spawned, so commands with the same key still execute in the order they were sent.
+    /// How the old runners stopped is returned.
+    ///
+    /// # Panics
+    /// Panics if `workers` is zero.
+    pub fn resize_with(
+        &mut self,
+        workers: usize,
+        mut s: impl StopRunner<Cmd>,
+    ) -> KeyedReport<Cmd, Ch> {
+        assert!(workers > 0, "a keyed pool needs at least one worker");
+        let shards = self.shards.clone();
+        // Submitters wait until the new runners are spawned
+        let mut senders = shards.senders_mut();
+        let old = self.stop_workers(&senders, &mut s);
+        let send_res = self.result_sender(&old);
+        *senders = self.spawn_workers(workers, &send_res);
+        old
+    }
+
+    /// See [`KeyedPoolAPI::resize_with`]
+    ///
+    /// # Panics
+    /// Panics if `workers` is zero.
+    pub fn resize(&mut self, workers: usize) -> KeyedReport<Cmd, Ch>
+    where
+        Cmd: SimpleStop,
+    {
+        self.resize_with(workers, SimpleCloser)
+    }
+
+    pub fn close_with(mut self, mut s: impl StopRunner<Cmd>) -> KeyedReport<Cmd, Ch> {
+        self.gate.close();
+        let shards = self.shards.clone();
+        self.stop_workers(&shards.senders(), &mut s)
+    }
+
+    pub fn close(self) -> KeyedReport<Cmd, Ch>
+    where
+        Cmd: SimpleStop,
+    {
+        self.close_with(SimpleCloser)
+    }
+
+    /// Same as [`KeyedPoolAPI::close_with`], but only waits `timeout` for the runners to stop
+    ///
+    /// See [`CloseTimeout`](crate::CloseTimeout) for what happens to the ones that don't.
+    pub fn close_timeout_with(
+        mut self,
+        mut s: impl StopRunner<Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<KeyedRunner<Cmd, Ch>, CmdRst<Cmd>> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        for sender in self.shards.senders().iter() {
+            // Not sent in time, or the worker already stopped, which joining it reports
+            let _ = send_until(sender, s.get(), deadline);
+        }
+        TimeoutReport::join_until(std::mem::take(&mut self.threads), deadline)
+    }
+
+    pub fn close_timeout(
+        self,
+        timeout: Duration,
+    ) -> TimeoutReport<KeyedRunner<Cmd, Ch>, CmdRst<Cmd>>
+    where
+        Cmd: SimpleStop,
+    {
+        self.close_timeout_with(SimpleCloser, timeout)
+    }
+
+    /// # Errors
+    /// Fails once every runner has stopped and every result was received.
+    pub fn recv(&self) -> Result<CmdRst<Cmd>, mpmc::RecvError> {
+        self.recv_res.recv()
+    }
+    /// # Errors
+    /// Fails if no result is available.
+    pub fn try_recv(&self) -> Result<CmdRst<Cmd>, mpmc::TryRecvError> {
+        self.recv_res.try_recv()
+    }
+    /// See [`ResultQueue::recv_timeout`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives within `timeout`, or if every runner has stopped.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<CmdRst<Cmd>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_timeout(self, timeout)
+    }
+    /// See [`ResultQueue::recv_deadline`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives before `deadline`, or if every runner has stopped.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<CmdRst<Cmd>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_deadline(self, deadline)
+    }
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    #[must_use]
+    pub fn iter(&self) -> mpmc::Iter<'_, CmdRst<Cmd>> {
+        ResultQueue::iter(self)
+    }
+    /// Results that are available, without blocking
+    #[must_use]
+    pub fn try_iter(&self) -> mpmc::TryIter<'_, CmdRst<Cmd>> {
+        ResultQueue::try_iter(self)
+    }
+    /// Collects every result that's available right now
+    #[must_use]
+    pub fn drain(&self) -> Vec<CmdRst<Cmd>> {
+        ResultQueue::drain(self)
+    }
+}
+
+impl<Cmd, Ch> ResultQueue for KeyedPoolAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    type Item = CmdRst<Cmd>;
+    fn results(&self) -> &mpmc::Receiver<Self::Item> {
+        &self.recv_res
+    }
+}
+
+impl<'a, Cmd, Ch> IntoIterator for &'a KeyedPoolAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    type Item = CmdRst<Cmd>;
+    type IntoIter = mpmc::Iter<'a, CmdRst<Cmd>>;
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -1,77 +1,178 @@
-use crate::queue::{QueueEventLoopError, QueueRunner};
-use crate::{CmdRst, Command, CommandRunner};
+use crate::channel::{Channel, Crossbeam, SharedChannel};
+use crate::close::{self, send_until};
+use crate::error::{EventLoopError, SendError};
+use crate::queue::QueueRunner;
+use crate::runtime::Runners;
+use crate::submit::{Gate, Submitter};
+use crate::sync::thread::JoinHandle;
+use crate::{
+    ChanSend, CloseReport, CloseTimeout, CmdRst, Command, CommandRunner, ResultQueue, RunnerConfig,
+    TimeoutReport,
+};
 use crossbeam_channel as mpmc;
-use std::any::Any;
-use std::sync::mpsc;
-use std::thread::JoinHandle;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-type MR<Cmd> = mpmc::Receiver<Cmd>;
-type SS<Cmd> = mpsc::Sender<CmdRst<Cmd>>;
-type PoolRunner<Cmd> = QueueRunner<Cmd, MR<Cmd>, SS<Cmd>>;
+type SS<Cmd> = mpmc::Sender<CmdRst<Cmd>>;
+type PoolRunner<Cmd, Ch> = QueueRunner<Cmd, <Ch as Channel>::Receiver<Cmd>, SS<Cmd>>;
+type Worker<Cmd, Ch> = JoinHandle<Result<PoolRunner<Cmd, Ch>, EventLoopError<CmdRst<Cmd>>>>;
 
 /// API of [`QueueRunner`] for managing multiple runners
-pub struct PoolQueueAPI<Cmd, const N: usize>
+///
+/// Commands are queued in a `Ch` [channel](crate::channel), [`crossbeam_channel`]'s by default.
+pub struct PoolQueueAPI<Cmd, const N: usize, Ch = Crossbeam>
 where
     Cmd: Command,
+    Ch: SharedChannel,
 {
-    send_cmd: mpmc::Sender<Cmd>,
-    recv_res: mpsc::Receiver<CmdRst<Cmd>>,
-    runners: [JoinHandle<Result<PoolRunner<Cmd>, QueueEventLoopError>>; N],
+    send_cmd: Ch::Sender<Cmd>,
+    recv_res: mpmc::Receiver<CmdRst<Cmd>>,
+    runners: [Worker<Cmd, Ch>; N],
+    gate: Arc<Gate>,
 }
 
-#[derive(Debug)]
-pub enum PoolQueueCloseError<Cmd>
-where
-    Cmd: Command,
-{
-    Send(mpmc::SendError<Cmd>),
-    Join(Box<dyn Any + Send>),
-}
-
-impl<Cmd, const N: usize> CommandRunner for PoolQueueAPI<Cmd, N>
+impl<Cmd, const N: usize, Ch> CommandRunner for PoolQueueAPI<Cmd, N, Ch>
 where
     Cmd: Command,
+    Ch: SharedChannel,
 {
     type Cmd = Cmd;
-    type SendAck = Result<(), mpmc::SendError<Cmd>>;
-    type CloseResult =
-        Result<[Result<PoolRunner<Cmd>, QueueEventLoopError>; N], mpmc::SendError<Cmd>>;
-    unsafe fn new() -> Self {
-        let (tx_cmd, rx_cmd) = mpmc::unbounded();
-        let (tx_res, rx_res) = mpsc::channel();
-        let runners = [(); N].map(|()| QueueRunner::spawn(rx_cmd.clone(), tx_res.clone()));
+    type SendAck = Result<(), SendError<Cmd>>;
+    type CloseResult = CloseReport<Cmd, PoolRunner<Cmd, Ch>, CmdRst<Cmd>>;
+    unsafe fn with_config(config: &RunnerConfig) -> Self {
+        let (tx_cmd, rx_cmd) = Ch::channel();
+        let (tx_res, rx_res) = mpmc::unbounded();
+        let runners = std::array::from_fn(|i| {
+            QueueRunner::spawn(config, "supera-pool", i, Ch::share(&rx_cmd), tx_res.clone())
+        });
         Self {
             send_cmd: tx_cmd,
             recv_res: rx_res,
             runners,
+            gate: Arc::default(),
         }
     }
     fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
-        self.send_cmd.send(cmd)
+        self.send_cmd.send_t(cmd).map_err(Into::into)
     }
     fn close_with(self, mut s: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
-        for _ in 0..self.runners.len() {
-            self.send(s.get())?;
+        self.gate.close();
+        for _ in 0..N {
+            // Sending only fails once every runner stopped, which joining them reports
+            if self.send(s.get()).is_err() {
+                break;
+            }
         }
-        Ok(self
-            .runners
-            .map(std::thread::JoinHandle::join)
-            .map(|e| e.map_err(QueueEventLoopError::ThreadPanic)?))
+        CloseReport::join(self.runners)
     }
 }
 
-impl<Cmd, const N: usize> PoolQueueAPI<Cmd, N>
+impl<Cmd, const N: usize, Ch> CloseTimeout for PoolQueueAPI<Cmd, N, Ch>
 where
     Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Runner = PoolRunner<Cmd, Ch>;
+    type Rst = CmdRst<Cmd>;
+    fn close_timeout_with(
+        self,
+        mut s: impl crate::StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        for _ in 0..N {
+            // Not sent in time, or every runner stopped, which joining them reports
+            if send_until(&self.send_cmd, s.get(), deadline).is_err() {
+                break;
+            }
+        }
+        TimeoutReport::join_until(self.runners, deadline)
+    }
+}
+
+impl<Cmd, const N: usize, Ch> Runners for PoolQueueAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    const RUNNERS: usize = N;
+}
+
+impl<Cmd, const N: usize, Ch> PoolQueueAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
 {
     /// # Errors
     /// An error would occour if the [runner](QueueRunner) was closed but the [api](QueueAPI) was not dropped.
-    pub fn recv(&self) -> Result<CmdRst<Cmd>, mpsc::RecvError> {
+    pub fn recv(&self) -> Result<CmdRst<Cmd>, mpmc::RecvError> {
         self.recv_res.recv()
     }
     /// # Errors
     /// An error would occour if the [runner](QueueRunner) was closed but the [api](QueueAPI) was not dropped.
-    pub fn try_recv(&self) -> Result<CmdRst<Cmd>, mpsc::TryRecvError> {
+    pub fn try_recv(&self) -> Result<CmdRst<Cmd>, mpmc::TryRecvError> {
         self.recv_res.try_recv()
     }
+    /// See [`ResultQueue::recv_timeout`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives within `timeout`, or if every runner has stopped.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<CmdRst<Cmd>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_timeout(self, timeout)
+    }
+    /// See [`ResultQueue::recv_deadline`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives before `deadline`, or if every runner has stopped.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<CmdRst<Cmd>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_deadline(self, deadline)
+    }
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    #[must_use]
+    pub fn iter(&self) -> mpmc::Iter<'_, CmdRst<Cmd>> {
+        ResultQueue::iter(self)
+    }
+    /// Results that are available, without blocking
+    #[must_use]
+    pub fn try_iter(&self) -> mpmc::TryIter<'_, CmdRst<Cmd>> {
+        ResultQueue::try_iter(self)
+    }
+    /// Collects every result that's available right now
+    #[must_use]
+    pub fn drain(&self) -> Vec<CmdRst<Cmd>> {
+        ResultQueue::drain(self)
+    }
+    /// Handle that can send commands from other threads, until the manager is closed
+    #[must_use]
+    pub fn submitter(&self) -> Submitter<Cmd, Ch::Sender<Cmd>>
+    where
+        Ch::Sender<Cmd>: Clone,
+    {
+        Submitter::new(self.send_cmd.clone(), self.gate.clone())
+    }
+}
+
+impl<Cmd, const N: usize, Ch> ResultQueue for PoolQueueAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Item = CmdRst<Cmd>;
+    fn results(&self) -> &mpmc::Receiver<Self::Item> {
+        &self.recv_res
+    }
+}
+
+impl<'a, Cmd, const N: usize, Ch> IntoIterator for &'a PoolQueueAPI<Cmd, N, Ch>
+where
+    Cmd: Command,
+    Ch: SharedChannel,
+{
+    type Item = CmdRst<Cmd>;
+    type IntoIter = mpmc::Iter<'a, CmdRst<Cmd>>;
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
@@ -1,89 +1,171 @@
-use crate::queue::{QueueEventLoopError, QueueRunner};
-use crate::{CmdRst, Command, CommandRunner};
-use std::any::Any;
-use std::fmt;
-use std::sync::mpsc::{self, Receiver, RecvError, SendError, Sender};
-use std::thread::JoinHandle;
+use crate::channel::{Channel, Std};
+use crate::close::{self, send_until};
+use crate::error::{CloseError, EventLoopError, SendError};
+use crate::queue::QueueRunner;
+use crate::runtime::Runners;
+use crate::submit::{Gate, Submitter};
+use crate::sync::thread::JoinHandle;
+use crate::{
+    ChanSend, CloseTimeout, CmdRst, Command, CommandRunner, ResultQueue, RunnerConfig,
+    TimeoutReport,
+};
+use crossbeam_channel as mpmc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-type SR<Cmd> = mpsc::Receiver<Cmd>;
-type SS<Cmd> = mpsc::Sender<CmdRst<Cmd>>;
+type SS<Cmd> = mpmc::Sender<CmdRst<Cmd>>;
+type SingleRunner<Cmd, Ch> = QueueRunner<Cmd, <Ch as Channel>::Receiver<Cmd>, SS<Cmd>>;
+type Worker<Cmd, Ch> = JoinHandle<Result<SingleRunner<Cmd, Ch>, EventLoopError<CmdRst<Cmd>>>>;
 
-/// API of [`QueueRunner`] for managing a single runner
-pub struct SingleQueueAPI<Cmd>
-where
-    Cmd: Command,
-{
-    send_cmd: Sender<Cmd>,
-    recv_res: Receiver<CmdRst<Cmd>>,
-    thread: JoinHandle<Result<QueueRunner<Cmd, SR<Cmd>, SS<Cmd>>, QueueEventLoopError>>,
-}
+/// [`SingleQueueChanAPI`] with it's commands queued in [`std`]'s channel
+pub type SingleQueueAPI<Cmd> = SingleQueueChanAPI<Cmd, Std>;
 
-#[derive(Debug)]
-pub enum SingleQueueCloseError<Cmd>
+/// API of [`QueueRunner`] for managing a single runner
+///
+/// Commands are queued in a `Ch` [channel](crate::channel).
+pub struct SingleQueueChanAPI<Cmd, Ch>
 where
     Cmd: Command,
+    Ch: Channel,
 {
-    Send(SendError<Cmd>),
-    Join(Box<dyn Any + Send>),
-    Worker(QueueEventLoopError),
+    send_cmd: Ch::Sender<Cmd>,
+    recv_res: mpmc::Receiver<CmdRst<Cmd>>,
+    thread: Worker<Cmd, Ch>,
+    gate: Arc<Gate>,
 }
 
-impl<Cmd: Command> fmt::Display for SingleQueueCloseError<Cmd> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Join(e) => write!(f, "Failed to join thread, {e:?}"),
-            Self::Send(cmd) => write!(f, "Failed to send command {cmd}"),
-            Self::Worker(e) => write!(f, "Worker failed with: {e}"),
-        }
-    }
-}
-
-impl<Cmd: fmt::Debug + Command> std::error::Error for SingleQueueCloseError<Cmd> {}
-
-impl<Cmd> CommandRunner for SingleQueueAPI<Cmd>
+impl<Cmd, Ch> CommandRunner for SingleQueueChanAPI<Cmd, Ch>
 where
     Cmd: Command,
+    Ch: Channel,
 {
     type Cmd = Cmd;
     type SendAck = Result<(), SendError<Cmd>>;
-    type CloseResult = Result<QueueRunner<Cmd, SR<Cmd>, SS<Cmd>>, SingleQueueCloseError<Cmd>>;
-    unsafe fn new() -> Self {
-        let (send_cmd, recv_cmd) = mpsc::channel();
-        let (send_res, recv_res) = mpsc::channel();
-        let thread = QueueRunner::spawn(recv_cmd, send_res);
-        SingleQueueAPI {
+    type CloseResult = Result<SingleRunner<Cmd, Ch>, CloseError<Cmd, CmdRst<Cmd>>>;
+    unsafe fn with_config(config: &RunnerConfig) -> Self {
+        let (send_cmd, recv_cmd) = Ch::channel();
+        let (send_res, recv_res) = mpmc::unbounded();
+        let thread = QueueRunner::spawn(config, "supera-queue", 0, recv_cmd, send_res);
+        SingleQueueChanAPI {
             send_cmd,
             recv_res,
             thread,
+            gate: Arc::default(),
         }
     }
     fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
-        self.send_cmd.send(cmd)
+        self.send_cmd.send_t(cmd).map_err(Into::into)
     }
     fn close_with(self, mut s: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
-        let cmd = s.get();
-        self.send_cmd
-            .send(cmd)
-            .map_err(SingleQueueCloseError::Send)?;
-        self.thread
-            .join()
-            .map_err(SingleQueueCloseError::Join)?
-            .map_err(SingleQueueCloseError::Worker)
+        self.gate.close();
+        // Sending only fails once the runner stopped, which joining it reports
+        let _ = self.send(s.get());
+        Ok(self.thread.join().map_err(EventLoopError::Panic)??)
     }
 }
 
-impl<Cmd> SingleQueueAPI<Cmd>
+impl<Cmd, Ch> CloseTimeout for SingleQueueChanAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    type Runner = SingleRunner<Cmd, Ch>;
+    type Rst = CmdRst<Cmd>;
+    fn close_timeout_with(
+        self,
+        mut s: impl crate::StopRunner<Self::Cmd>,
+        timeout: Duration,
+    ) -> TimeoutReport<Self::Runner, Self::Rst> {
+        let deadline = close::deadline(timeout);
+        self.gate.close();
+        // If it's not sent, the runner either stopped or is stuck, which joining it reports
+        let _ = send_until(&self.send_cmd, s.get(), deadline);
+        TimeoutReport::join_until([self.thread], deadline)
+    }
+}
+
+impl<Cmd, Ch> Runners for SingleQueueChanAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    const RUNNERS: usize = 1;
+}
+
+impl<Cmd, Ch> SingleQueueChanAPI<Cmd, Ch>
 where
     Cmd: Command,
+    Ch: Channel,
 {
     /// # Errors
     /// An error would occour if the [runner](QueueRunner) was closed but the [api](QueueAPI) was not dropped.
-    pub fn recv(&self) -> Result<CmdRst<Cmd>, RecvError> {
+    pub fn recv(&self) -> Result<CmdRst<Cmd>, mpmc::RecvError> {
         self.recv_res.recv()
     }
     /// # Errors
     /// An error would occour if the [runner](QueueRunner) was closed but the [api](QueueAPI) was not dropped.
-    pub fn try_recv(&self) -> Result<CmdRst<Cmd>, mpsc::TryRecvError> {
+    pub fn try_recv(&self) -> Result<CmdRst<Cmd>, mpmc::TryRecvError> {
         self.recv_res.try_recv()
     }
+    /// See [`ResultQueue::recv_timeout`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives within `timeout`, or if every runner has stopped.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<CmdRst<Cmd>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_timeout(self, timeout)
+    }
+    /// See [`ResultQueue::recv_deadline`]
+    ///
+    /// # Errors
+    /// Fails if no result arrives before `deadline`, or if every runner has stopped.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<CmdRst<Cmd>, mpmc::RecvTimeoutError> {
+        ResultQueue::recv_deadline(self, deadline)
+    }
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    #[must_use]
+    pub fn iter(&self) -> mpmc::Iter<'_, CmdRst<Cmd>> {
+        ResultQueue::iter(self)
+    }
+    /// Results that are available, without blocking
+    #[must_use]
+    pub fn try_iter(&self) -> mpmc::TryIter<'_, CmdRst<Cmd>> {
+        ResultQueue::try_iter(self)
+    }
+    /// Collects every result that's available right now
+    #[must_use]
+    pub fn drain(&self) -> Vec<CmdRst<Cmd>> {
+        ResultQueue::drain(self)
+    }
+    /// Handle that can send commands from other threads, until the manager is closed
+    #[must_use]
+    pub fn submitter(&self) -> Submitter<Cmd, Ch::Sender<Cmd>>
+    where
+        Ch::Sender<Cmd>: Clone,
+    {
+        Submitter::new(self.send_cmd.clone(), self.gate.clone())
+    }
+}
+
+impl<Cmd, Ch> ResultQueue for SingleQueueChanAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    type Item = CmdRst<Cmd>;
+    fn results(&self) -> &mpmc::Receiver<Self::Item> {
+        &self.recv_res
+    }
+}
+
+impl<'a, Cmd, Ch> IntoIterator for &'a SingleQueueChanAPI<Cmd, Ch>
+where
+    Cmd: Command,
+    Ch: Channel,
+{
+    type Item = CmdRst<Cmd>;
+    type IntoIter = mpmc::Iter<'a, CmdRst<Cmd>>;
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
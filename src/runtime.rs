@@ -0,0 +1,267 @@
+use crate::config::Counters;
+use crate::error::AnyCloseError;
+use crate::{CloseOutcome, CommandRunner, RunnerConfig, SimpleStop};
+use std::any::{Any, type_name};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+/// How many runner threads a manager spawns
+pub trait Runners {
+    const RUNNERS: usize;
+}
+
+type Closer = fn(Box<dyn Any + Send + Sync>) -> Vec<AnyCloseError>;
+
+/// Closes a type erased manager of type `M`
+fn close<M>(manager: Box<dyn Any + Send + Sync>) -> Vec<AnyCloseError>
+where
+    M: CommandRunner + 'static,
+    M::Cmd: SimpleStop,
+    <M::CloseResult as CloseOutcome>::Rst: Send + 'static,
+{
+    manager.downcast::<M>().map_or_else(
+        |_| Vec::new(),
+        |m| {
+            m.close()
+                .into_errors()
+                .into_iter()
+                .map(AnyCloseError::from)
+                .collect()
+        },
+    )
+}
+
+struct Pool {
+    name: String,
+    type_name: &'static str,
+    threads: usize,
+    /// Names of the managers it depends on
+    deps: Vec<String>,
+    counters: Arc<Counters>,
+    manager: Box<dyn Any + Send + Sync>,
+    close: Closer,
+}
+
+/// Why a [`Runtime`] refused to spawn a manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// A manager already has this name
+    Duplicate(String),
+    /// No manager has the name of this dependency
+    UnknownDependency(String),
+    /// Spawning the manager would exceed the thread budget
+    OverBudget { requested: usize, available: usize },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate(name) => write!(f, "A manager named {name} already exists"),
+            Self::UnknownDependency(name) => write!(f, "No manager is named {name}"),
+            Self::OverBudget {
+                requested,
+                available,
+            } => write!(
+                f,
+                "Requested {requested} threads, but only {available} are available"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// A manager owned by a [`Runtime`], and what it's runners did so far
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    pub name: String,
+    /// Type of the manager
+    pub type_name: &'static str,
+    pub threads: usize,
+    /// Commands the runners took off their queue, stop commands included
+    pub received: u64,
+    /// Commands that were executed, stop commands excluded
+    pub executed: u64,
+    /// Results no one could receive, see
+    /// [`RunnerConfig::on_abandoned`](crate::RunnerConfig::on_abandoned)
+    pub abandoned: u64,
+}
+
+/// Every manager owned by a [`Runtime`], in the order they were spawned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub pools: Vec<PoolStats>,
+    /// Runner threads of every manager
+    pub threads: usize,
+    pub budget: Option<usize>,
+}
+
+/// How a manager was closed by it's [`Runtime`]
+#[derive(Debug)]
+pub struct PoolReport {
+    pub name: String,
+    /// Every failure while closing it, which can be downcast to the manager's
+    /// [`CloseError`](crate::error::CloseError)
+    pub errors: Vec<AnyCloseError>,
+}
+
+/// Owns many named managers, each spawned with it's own [`RunnerConfig`]
+///
+/// Managers are closed in the order they were spawned, except that a manager is always closed
+/// before the ones it depends on, which must have been spawned before it. That's done by
+/// [`Runtime::close`], or when the runtime is dropped.
+#[derive(Default)]
+pub struct Runtime {
+    budget: Option<usize>,
+    pools: Vec<Pool>,
+}
+
+impl Runtime {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A runtime whose managers can't have more than `threads` runner threads in total
+    #[must_use]
+    pub fn with_budget(threads: usize) -> Self {
+        Self {
+            budget: Some(threads),
+            pools: Vec::new(),
+        }
+    }
+
+    /// Runner threads of every manager
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.pools.iter().map(|p| p.threads).sum()
+    }
+
+    /// Spawns a manager of type `M` named `name`, which is closed before every manager named in
+    /// `deps`
+    ///
+    /// Unlike [`CommandRunner::new`] it's safe, since the runtime always closes it's managers.
+    ///
+    /// # Errors
+    /// Fails if the name is taken, a dependency doesn't exist, or the manager's runners don't
+    /// fit the thread budget.
+    pub fn spawn<M>(
+        &mut self,
+        name: impl Into<String>,
+        config: &RunnerConfig,
+        deps: &[&str],
+    ) -> Result<&M, RuntimeError>
+    where
+        M: CommandRunner + Runners + Send + Sync + 'static,
+        M::Cmd: SimpleStop,
+        <M::CloseResult as CloseOutcome>::Rst: Send + 'static,
+    {
+        let name = name.into();
+        if self.pools.iter().any(|p| p.name == name) {
+            return Err(RuntimeError::Duplicate(name));
+        }
+        if let Some(dep) = deps
+            .iter()
+            .find(|d| self.pools.iter().all(|p| p.name != **d))
+        {
+            return Err(RuntimeError::UnknownDependency((*dep).to_string()));
+        }
+        if let Some(budget) = self.budget {
+            let available = budget.saturating_sub(self.threads());
+            if M::RUNNERS > available {
+                return Err(RuntimeError::OverBudget {
+                    requested: M::RUNNERS,
+                    available,
+                });
+            }
+        }
+        let counters = Arc::new(Counters::default());
+        let manager = unsafe { M::with_config(&config.clone().counted(counters.clone())) };
+        self.pools.push(Pool {
+            name,
+            type_name: type_name::<M>(),
+            threads: M::RUNNERS,
+            deps: deps.iter().map(ToString::to_string).collect(),
+            counters,
+            manager: Box::new(manager),
+            close: close::<M>,
+        });
+        Ok(self.get_last())
+    }
+
+    /// # Panics
+    /// Panics if the last manager isn't an `M`, it's only called right after pushing one.
+    fn get_last<M: 'static>(&self) -> &M {
+        self.pools
+            .last()
+            .and_then(|p| p.manager.downcast_ref())
+            .expect("the last manager was just spawned")
+    }
+
+    /// The manager named `name`, if it's an `M`
+    #[must_use]
+    pub fn get<M: 'static>(&self, name: &str) -> Option<&M> {
+        self.pools
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.manager.downcast_ref())
+    }
+
+    /// The first manager spawned that's an `M`
+    #[must_use]
+    pub fn get_by_type<M: 'static>(&self) -> Option<&M> {
+        self.pools.iter().find_map(|p| p.manager.downcast_ref())
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        Stats {
+            pools: self
+                .pools
+                .iter()
+                .map(|p| PoolStats {
+                    name: p.name.clone(),
+                    type_name: p.type_name,
+                    threads: p.threads,
+                    received: p.counters.received.load(Ordering::Relaxed),
+                    executed: p.counters.executed.load(Ordering::Relaxed),
+                    abandoned: p.counters.abandoned.load(Ordering::Relaxed),
+                })
+                .collect(),
+            threads: self.threads(),
+            budget: self.budget,
+        }
+    }
+
+    /// Closes every manager, each one before the managers it depends on
+    #[must_use]
+    pub fn close(mut self) -> Vec<PoolReport> {
+        self.close_all()
+    }
+
+    fn close_all(&mut self) -> Vec<PoolReport> {
+        let mut pools = std::mem::take(&mut self.pools);
+        let mut reports = Vec::with_capacity(pools.len());
+        while !pools.is_empty() {
+            // The first one spawned that no other depends on, there's always one since the
+            // last one spawned can't be depended on
+            let next = pools
+                .iter()
+                .position(|p| pools.iter().all(|o| !o.deps.contains(&p.name)))
+                .unwrap_or(pools.len() - 1);
+            let p = pools.remove(next);
+            reports.push(PoolReport {
+                errors: (p.close)(p.manager),
+                name: p.name,
+            });
+        }
+        reports
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.close_all();
+    }
+}
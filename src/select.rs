@@ -0,0 +1,114 @@
+use crossbeam_channel as mpmc;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A manager whose results come out of a queue
+pub trait ResultQueue {
+    type Item;
+    fn results(&self) -> &mpmc::Receiver<Self::Item>;
+
+    /// # Errors
+    /// Fails if no result arrives within `timeout`, or if every runner has stopped.
+    fn recv_timeout(&self, timeout: Duration) -> Result<Self::Item, mpmc::RecvTimeoutError> {
+        self.results().recv_timeout(timeout)
+    }
+    /// # Errors
+    /// Fails if no result arrives before `deadline`, or if every runner has stopped.
+    fn recv_deadline(&self, deadline: Instant) -> Result<Self::Item, mpmc::RecvTimeoutError> {
+        self.results().recv_deadline(deadline)
+    }
+    /// Blocks waiting for each result, only ends once every runner has stopped
+    fn iter(&self) -> mpmc::Iter<'_, Self::Item> {
+        self.results().iter()
+    }
+    /// Results that are available, without blocking
+    fn try_iter(&self) -> mpmc::TryIter<'_, Self::Item> {
+        self.results().try_iter()
+    }
+    /// Collects every result that's available right now
+    fn drain(&self) -> Vec<Self::Item> {
+        self.try_iter().collect()
+    }
+}
+
+type Arm<'a, T> = Box<dyn FnMut(mpmc::SelectedOperation<'a>) -> Result<T, mpmc::RecvError> + 'a>;
+
+/// Waits on the results of many [`ResultQueue`]s at once
+///
+/// Each queue is added with a function that converts it's results to a common type `T`.
+pub struct Select<'a, T> {
+    sel: mpmc::Select<'a>,
+    arms: Vec<Arm<'a, T>>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectError {
+    Timeout,
+    /// Every runner of the queue added in this position has stopped
+    Disconnected(usize),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "Timed out waiting for results"),
+            Self::Disconnected(i) => write!(f, "Queue {i} is disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for SelectError {}
+
+impl<T> Default for Select<'_, T> {
+    fn default() -> Self {
+        Self {
+            sel: mpmc::Select::new(),
+            arms: Vec::new(),
+            timeout: None,
+        }
+    }
+}
+
+impl<'a, T> Select<'a, T> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn recv<Q: ResultQueue>(
+        mut self,
+        queue: &'a Q,
+        mut f: impl FnMut(Q::Item) -> T + 'a,
+    ) -> Self {
+        let results = queue.results();
+        self.sel.recv(results);
+        self.arms
+            .push(Box::new(move |op| op.recv(results).map(&mut f)));
+        self
+    }
+
+    /// Stop waiting for results after `timeout`
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Blocks until any of the queues has a result
+    ///
+    /// # Errors
+    /// Fails if the timeout passes or if the queue that was ready is disconnected.
+    pub fn wait(&mut self) -> Result<T, SelectError> {
+        let op = match self.timeout {
+            None => self.sel.select(),
+            Some(timeout) => self
+                .sel
+                .select_timeout(timeout)
+                .map_err(|_| SelectError::Timeout)?,
+        };
+        let index = op.index();
+        (self.arms[index])(op).map_err(|_| SelectError::Disconnected(index))
+    }
+}
@@ -0,0 +1,365 @@
+//! A global registry of live managers, so a process can close all of them at once, e.g. when
+//! it's asked to terminate
+//!
+//! Managers are handed to [`register`], which gives back a [`Managed`] handle to use them
+//! through. [`shutdown_all`] then closes every registered manager, following the [`Mode`] it was
+//! registered with. On Linux, [`handle_signals`] does so once the process gets `SIGTERM` or
+//! `SIGINT`.
+use crate::error::{AnyCloseError, CloseError};
+use crate::{CloseOutcome, CloseTimeout, CommandRunner, ResultQueue, SimpleStop};
+use crossbeam_channel as mpmc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+
+/// How a registered manager is closed by [`shutdown_all`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// With [`CommandRunner::close`], executing every command that's queued
+    #[default]
+    Drain,
+    /// With [`CloseTimeout::close_timeout`], detaching the runners that don't stop in time
+    Timeout(Duration),
+}
+
+/// How a manager was closed by [`shutdown_all`]
+#[derive(Debug)]
+pub struct Report {
+    pub name: String,
+    /// Every failure while closing it, which can be downcast to the manager's [`CloseError`]
+    pub errors: Vec<AnyCloseError>,
+    /// How many runners were detached, for [`Mode::Timeout`]
+    pub unfinished: usize,
+}
+
+impl Report {
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty() && self.unfinished == 0
+    }
+}
+
+/// A registered manager, type erased
+trait Shutdown: Send + Sync {
+    /// Closes the manager, unless it was already
+    fn shutdown(&self, mode: Mode) -> Option<Report>;
+}
+
+struct Entry {
+    id: u64,
+    mode: Mode,
+    manager: Arc<dyn Shutdown>,
+}
+
+static REGISTRY: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+struct Inner<M> {
+    id: u64,
+    name: String,
+    /// `None` once it's closed
+    manager: RwLock<Option<M>>,
+}
+
+impl<M> Shutdown for Inner<M>
+where
+    M: CloseTimeout + Send + Sync,
+    M::Cmd: SimpleStop,
+    M::Rst: Send + 'static,
+    <M::CloseResult as CloseOutcome>::Rst: Send + 'static,
+{
+    fn shutdown(&self, mode: Mode) -> Option<Report> {
+        let manager = self
+            .manager
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()?;
+        let mut report = Report {
+            name: self.name.clone(),
+            errors: Vec::new(),
+            unfinished: 0,
+        };
+        match mode {
+            Mode::Drain => {
+                report.errors = manager
+                    .close()
+                    .into_errors()
+                    .into_iter()
+                    .map(AnyCloseError::from)
+                    .collect();
+            }
+            Mode::Timeout(timeout) => {
+                let timed = manager.close_timeout(timeout);
+                report.unfinished = timed.unfinished.len();
+                report.errors = timed
+                    .stopped
+                    .into_iter()
+                    .filter_map(|(_, r)| r.err())
+                    .map(|e| AnyCloseError::from(CloseError::<M::Cmd, M::Rst>::Worker(e)))
+                    .collect();
+            }
+        }
+        Some(report)
+    }
+}
+
+/// Handle to a manager in the registry
+///
+/// Dropping it leaves the manager registered, so it's still closed by [`shutdown_all`].
+pub struct Managed<M> {
+    inner: Arc<Inner<M>>,
+}
+
+impl<M> Clone for Managed<M> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<M> Managed<M>
+where
+    M: CommandRunner,
+{
+    /// Calls `f` with the manager, or returns `None` if it was already closed
+    ///
+    /// [`shutdown_all`] waits for every call to return before closing it, so `f` must not
+    /// block, e.g. waiting for a result. Take what's needed instead, such as a submitter, or
+    /// wait on [`Managed::results`].
+    pub fn with<R>(&self, f: impl FnOnce(&M) -> R) -> Option<R> {
+        let manager = self
+            .inner
+            .manager
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        manager.as_ref().map(f)
+    }
+
+    /// Sends `cmd` to the manager, giving it back if it was already closed
+    ///
+    /// # Errors
+    /// Fails if the manager was closed by [`shutdown_all`].
+    pub fn send(&self, cmd: M::Cmd) -> Result<M::SendAck, M::Cmd> {
+        let manager = self
+            .inner
+            .manager
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        match manager.as_ref() {
+            Some(m) => Ok(m.send(cmd)),
+            None => Err(cmd),
+        }
+    }
+
+    /// Takes the manager out of the registry, to close it by hand
+    ///
+    /// Returns `None` if it was already closed.
+    #[must_use]
+    pub fn unregister(self) -> Option<M> {
+        REGISTRY
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|e| e.id != self.inner.id);
+        self.inner
+            .manager
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+}
+
+impl<M> Managed<M>
+where
+    M: CommandRunner + ResultQueue,
+{
+    /// The manager's result queue, or `None` if it was already closed
+    ///
+    /// It can be waited on without holding back [`shutdown_all`], receiving fails once the
+    /// manager was closed and every result was received.
+    #[must_use]
+    pub fn results(&self) -> Option<mpmc::Receiver<M::Item>> {
+        self.with(|m| m.results().clone())
+    }
+}
+
+/// Adds `manager` to the registry, to be closed by [`shutdown_all`] following `mode`
+pub fn register<M>(name: impl Into<String>, mode: Mode, manager: M) -> Managed<M>
+where
+    M: CloseTimeout + Send + Sync + 'static,
+    M::Cmd: SimpleStop,
+    M::Rst: Send + 'static,
+    <M::CloseResult as CloseOutcome>::Rst: Send + 'static,
+{
+    let inner = Arc::new(Inner {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        name: name.into(),
+        manager: RwLock::new(Some(manager)),
+    });
+    REGISTRY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Entry {
+            id: inner.id,
+            mode,
+            manager: inner.clone(),
+        });
+    Managed { inner }
+}
+
+/// How many managers are registered
+#[must_use]
+pub fn registered() -> usize {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .len()
+}
+
+/// Closes every registered manager, the last one registered first, so managers that feed
+/// others are closed before them
+pub fn shutdown_all() -> Vec<Report> {
+    let entries = std::mem::take(&mut *REGISTRY.lock().unwrap_or_else(PoisonError::into_inner));
+    entries
+        .into_iter()
+        .rev()
+        .filter_map(|e| e.manager.shutdown(e.mode))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub use signal::handle_signals;
+
+#[cfg(target_os = "linux")]
+mod signal {
+    use super::{Report, shutdown_all};
+    use std::io;
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    /// Write end of the self-pipe, -1 until the handler is installed
+    static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    /// Only async-signal-safe calls are made here, the signal is handled by the watcher thread
+    extern "C" fn on_signal(sig: libc::c_int) {
+        // SAFETY: the errno location is valid for the current thread
+        let errno = unsafe { *libc::__errno_location() };
+        // Signal numbers fit a byte on Linux
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let byte = sig as u8;
+        // SAFETY: write is async-signal-safe, and the pipe is never closed once this handler was
+        // installed, see `uninstall`. If it's full a shutdown is already pending, so the byte
+        // can be lost, and once it's uninstalled the write fails on -1.
+        unsafe { libc::write(PIPE.load(Ordering::Relaxed), (&raw const byte).cast(), 1) };
+        // SAFETY: see above
+        unsafe { *libc::__errno_location() = errno };
+    }
+
+    /// Closes every registered manager once the process gets `SIGTERM` or `SIGINT`, then calls
+    /// `then` with the signal and the reports of [`shutdown_all`]
+    ///
+    /// The handler writes to a pipe, which a thread named `supera-signal` waits on, so nothing
+    /// but that write happens in the handler itself. `then` usually exits the process. Once the
+    /// first signal is read the previous actions are put back, so another `SIGTERM` or `SIGINT`
+    /// isn't swallowed while the managers are closed, and by default terminates the process.
+    /// The pipe is never closed, so each handler installed leaks two file descriptors.
+    ///
+    /// # Errors
+    /// Fails if the handler was already installed, or the pipe, thread or handler could not be
+    /// set up.
+    pub fn handle_signals(then: impl FnOnce(i32, Vec<Report>) + Send + 'static) -> io::Result<()> {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for both ends
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let [read, write] = fds;
+        // SAFETY: `write` is an open file descriptor, setting a flag has no other effect
+        if unsafe { libc::fcntl(write, libc::F_SETFL, libc::O_NONBLOCK) } != 0 {
+            let e = io::Error::last_os_error();
+            close(fds);
+            return Err(e);
+        }
+        if PIPE
+            .compare_exchange(-1, write, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            close(fds);
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the signal handler is already installed",
+            ));
+        }
+        // The handlers are installed first, a signal that comes before the thread is spawned
+        // waits in the pipe
+        let mut previous = Vec::new();
+        for sig in [libc::SIGTERM, libc::SIGINT] {
+            // SAFETY: `sigaction` is a plain struct, all zeros is an empty mask with no flags
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = on_signal as *const () as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            // SAFETY: see above
+            let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+            // SAFETY: both actions are valid, and `on_signal` only makes async-signal-safe calls
+            if unsafe { libc::sigaction(sig, &raw const action, &raw mut old) } != 0 {
+                let e = io::Error::last_os_error();
+                uninstall(&previous);
+                return Err(e);
+            }
+            previous.push((sig, old));
+        }
+        let restore = previous.clone();
+        let spawned = std::thread::Builder::new()
+            .name("supera-signal".into())
+            .spawn(move || {
+                let sig = wait(read);
+                // A signal that comes while the managers are closed, e.g. because one hangs, gets
+                // the action that was replaced
+                uninstall(&previous);
+                if let Some(sig) = sig {
+                    then(sig, shutdown_all());
+                }
+            });
+        match spawned {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                // The closure was dropped along with it's copy of the actions
+                uninstall(&restore);
+                Err(e)
+            }
+        }
+    }
+
+    /// Puts the actions that were replaced back, so another handler can be installed
+    ///
+    /// The pipe is leaked rather than closed: a handler already running on another thread may
+    /// have loaded the write end before it's reset, and would then write to whatever reused it's
+    /// number. With the read end open too, such a late byte just stays in the pipe.
+    fn uninstall(previous: &[(libc::c_int, libc::sigaction)]) {
+        for (sig, old) in previous {
+            // SAFETY: `old` was given by `sigaction` itself
+            unsafe { libc::sigaction(*sig, old, std::ptr::null_mut()) };
+        }
+        PIPE.store(-1, Ordering::SeqCst);
+    }
+
+    /// Blocks until a signal's byte is read, returns `None` if reading fails
+    fn wait(read: libc::c_int) -> Option<i32> {
+        let mut byte = 0u8;
+        loop {
+            // SAFETY: `byte` has room for the one byte read
+            match unsafe { libc::read(read, (&raw mut byte).cast(), 1) } {
+                1 => return Some(i32::from(byte)),
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                _ => return None,
+            }
+        }
+    }
+
+    /// Closes the pipe before any handler could write to it
+    fn close(fds: [libc::c_int; 2]) {
+        for fd in fds {
+            // SAFETY: both ends are open, and not used anywhere else yet
+            unsafe { libc::close(fd) };
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use crate::close;
+use crate::error::SendError;
+use crate::oneshot::{ExternalCommandLink, QueuedCommand, Reply};
+use crate::queue_envelope::{CommandId, Tagged, tag, untag};
+use crate::queue_keyed::Shards;
+use crate::sync::RwLock;
+use crate::{ChanSend, CmdRst, Command, CommandRunner, SendOutcome};
+use crossbeam_channel as mpmc;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, PoisonError, mpsc};
+
+/// Shared between a manager and it's submitters, it's closed as soon as the manager starts
+/// closing. So every command a submitter manages to send is queued before the stop commands.
+#[derive(Debug, Default)]
+pub(crate) struct Gate(RwLock<bool>);
+
+impl Gate {
+    /// Tries to send `t` with `try_send` unless the gate is closed, the gate can't be closed
+    /// while it's sent
+    ///
+    /// The gate is only held while trying to send, so a full channel can't hold back closing
+    /// the manager, instead the sender naps outside of it and tries again.
+    ///
+    /// # Errors
+    /// Gives `t` back if the gate is closed or the channel is disconnected.
+    fn pass<T>(
+        &self,
+        mut t: T,
+        try_send: impl Fn(T) -> Result<(), mpmc::TrySendError<T>>,
+    ) -> Result<(), Closed<T>> {
+        loop {
+            let closed = self.0.read().unwrap_or_else(PoisonError::into_inner);
+            if *closed {
+                return Err(Closed(t));
+            }
+            match try_send(t) {
+                Ok(()) => return Ok(()),
+                Err(mpmc::TrySendError::Disconnected(back)) => return Err(Closed(back)),
+                Err(mpmc::TrySendError::Full(back)) => t = back,
+            }
+            drop(closed);
+            std::thread::sleep(close::POLL);
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = true;
+    }
+}
+
+/// The manager has closed, or is closing, so the command was not sent
+#[derive(Debug)]
+pub struct Closed<T>(pub T);
+
+impl<T> Closed<T> {
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Display for Closed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Manager is closed")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for Closed<T> {}
+
+impl<T> From<mpsc::SendError<T>> for Closed<T> {
+    fn from(e: mpsc::SendError<T>) -> Self {
+        Self(e.0)
+    }
+}
+
+impl<T> From<mpmc::SendError<T>> for Closed<T> {
+    fn from(e: mpmc::SendError<T>) -> Self {
+        Self(e.0)
+    }
+}
+
+impl<T> From<Closed<T>> for SendError<T> {
+    fn from(e: Closed<T>) -> Self {
+        Self(e.0)
+    }
+}
+
+/// Sends commands to runners, without knowing which manager they belong to
+///
+/// Every manager and submitter implements it, and unlike [`CommandRunner`] it can be used as a
+/// trait object, e.g. `&dyn Submit<Cmd, Ack = ()>` accepts any ordered manager.
+pub trait Submit<Cmd> {
+    /// What's given back for a command that was sent
+    type Ack;
+    /// # Errors
+    /// Fails if the command was not sent, giving it back.
+    fn submit(&self, cmd: Cmd) -> Result<Self::Ack, SendError<Cmd>>;
+}
+
+impl<R> Submit<R::Cmd> for R
+where
+    R: CommandRunner,
+{
+    type Ack = <R::SendAck as SendOutcome<R::Cmd>>::Ack;
+    fn submit(&self, cmd: R::Cmd) -> Result<Self::Ack, SendError<R::Cmd>> {
+        self.send(cmd).into_result()
+    }
+}
+
+/// Cloneable handle that sends commands to a queue manager's runners
+pub struct Submitter<Cmd, S> {
+    chan: S,
+    gate: Arc<Gate>,
+    d: PhantomData<fn(Cmd)>,
+}
+
+impl<Cmd, S> Submitter<Cmd, S> {
+    pub(crate) fn new(chan: S, gate: Arc<Gate>) -> Self {
+        Self {
+            chan,
+            gate,
+            d: PhantomData,
+        }
+    }
+}
+
+impl<Cmd, S: Clone> Clone for Submitter<Cmd, S> {
+    fn clone(&self) -> Self {
+        Self::new(self.chan.clone(), self.gate.clone())
+    }
+}
+
+impl<Cmd, S> Submitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<Cmd>,
+{
+    /// # Errors
+    /// Fails once the manager has started closing.
+    pub fn send(&self, cmd: Cmd) -> Result<(), Closed<Cmd>> {
+        self.gate.pass(cmd, |cmd| self.chan.try_send_t(cmd))
+    }
+}
+
+/// Cloneable handle that sends commands to a linked manager's runners
+pub struct LinkSubmitter<Cmd, S>
+where
+    Cmd: Command,
+{
+    chan: S,
+    gate: Arc<Gate>,
+    d: PhantomData<fn(Cmd)>,
+}
+
+impl<Cmd, S> LinkSubmitter<Cmd, S>
+where
+    Cmd: Command,
+{
+    pub(crate) fn new(chan: S, gate: Arc<Gate>) -> Self {
+        Self {
+            chan,
+            gate,
+            d: PhantomData,
+        }
+    }
+}
+
+impl<Cmd, S: Clone> Clone for LinkSubmitter<Cmd, S>
+where
+    Cmd: Command,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.chan.clone(), self.gate.clone())
+    }
+}
+
+impl<Cmd, S> LinkSubmitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<QueuedCommand<Cmd>>,
+{
+    /// # Errors
+    /// Fails once the manager has started closing.
+    pub fn send(&self, cmd: Cmd) -> Result<ExternalCommandLink<Cmd>, Closed<Cmd>> {
+        let (reply, rx) = Reply::link();
+        self.send_queued(QueuedCommand { cmd, reply })?;
+        Ok(rx)
+    }
+
+    /// Same as [`OneShotPoolAPI::send_with_callback`](crate::oneshot_pool::OneShotPoolAPI::send_with_callback)
+    ///
+    /// # Errors
+    /// Fails once the manager has started closing.
+    pub fn send_with_callback(
+        &self,
+        cmd: Cmd,
+        f: impl FnOnce(CmdRst<Cmd>) + Send + 'static,
+    ) -> Result<(), Closed<Cmd>> {
+        let reply = Reply::Callback(Box::new(f));
+        self.send_queued(QueuedCommand { cmd, reply })
+    }
+
+    /// # Errors
+    /// Fails once the manager has started closing.
+    fn send_queued(&self, msg: QueuedCommand<Cmd>) -> Result<(), Closed<Cmd>> {
+        self.gate
+            .pass(msg, |msg| self.chan.try_send_t(msg))
+            .map_err(|Closed(msg)| Closed(msg.cmd))
+    }
+}
+
+/// Cloneable handle that sends commands to an
+/// [`EnvelopePoolAPI`](crate::queue_envelope::EnvelopePoolAPI)'s runners
+pub struct EnvelopeSubmitter<Cmd, S> {
+    chan: S,
+    gate: Arc<Gate>,
+    next_id: Arc<AtomicU64>,
+    d: PhantomData<fn(Cmd)>,
+}
+
+impl<Cmd, S> EnvelopeSubmitter<Cmd, S> {
+    pub(crate) fn new(chan: S, gate: Arc<Gate>, next_id: Arc<AtomicU64>) -> Self {
+        Self {
+            chan,
+            gate,
+            next_id,
+            d: PhantomData,
+        }
+    }
+}
+
+impl<Cmd, S: Clone> Clone for EnvelopeSubmitter<Cmd, S> {
+    fn clone(&self) -> Self {
+        Self::new(self.chan.clone(), self.gate.clone(), self.next_id.clone())
+    }
+}
+
+impl<Cmd, S> EnvelopeSubmitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<Tagged<Cmd>>,
+{
+    /// # Errors
+    /// Fails once the manager has started closing.
+    pub fn send(&self, cmd: Cmd) -> Result<CommandId, Closed<Cmd>> {
+        let tagged = tag(&self.next_id, cmd);
+        let id = tagged.id();
+        self.gate
+            .pass(tagged, |tagged| self.chan.try_send_t(tagged))
+            .map(|()| id)
+            .map_err(|Closed(tagged)| Closed(untag(tagged)))
+    }
+}
+
+/// Cloneable handle that sends commands to a
+/// [`KeyedPoolAPI`](crate::queue_keyed::KeyedPoolAPI)'s runners
+pub struct KeyedSubmitter<Cmd, S> {
+    shards: Arc<Shards<S>>,
+    gate: Arc<Gate>,
+    d: PhantomData<fn(Cmd)>,
+}
+
+impl<Cmd, S> KeyedSubmitter<Cmd, S> {
+    pub(crate) fn new(shards: Arc<Shards<S>>, gate: Arc<Gate>) -> Self {
+        Self {
+            shards,
+            gate,
+            d: PhantomData,
+        }
+    }
+}
+
+impl<Cmd, S> Clone for KeyedSubmitter<Cmd, S> {
+    fn clone(&self) -> Self {
+        Self::new(self.shards.clone(), self.gate.clone())
+    }
+}
+
+impl<Cmd, S> KeyedSubmitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<Cmd>,
+{
+    /// # Errors
+    /// Fails once the manager has started closing.
+    pub fn send<K: Hash + ?Sized>(&self, key: &K, cmd: Cmd) -> Result<(), Closed<Cmd>> {
+        self.gate.pass(cmd, |cmd| {
+            let senders = self.shards.senders();
+            senders[self.shards.index(key, senders.len())].try_send_t(cmd)
+        })
+    }
+}
+
+impl<Cmd, S> Submit<Cmd> for Submitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<Cmd>,
+{
+    type Ack = ();
+    fn submit(&self, cmd: Cmd) -> Result<(), SendError<Cmd>> {
+        Ok(self.send(cmd)?)
+    }
+}
+
+impl<Cmd, S> Submit<Cmd> for EnvelopeSubmitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<Tagged<Cmd>>,
+{
+    type Ack = CommandId;
+    fn submit(&self, cmd: Cmd) -> Result<CommandId, SendError<Cmd>> {
+        Ok(self.send(cmd)?)
+    }
+}
+
+impl<Cmd, S> Submit<Cmd> for LinkSubmitter<Cmd, S>
+where
+    Cmd: Command,
+    S: ChanSend<QueuedCommand<Cmd>>,
+{
+    type Ack = ExternalCommandLink<Cmd>;
+    fn submit(&self, cmd: Cmd) -> Result<Self::Ack, SendError<Cmd>> {
+        Ok(self.send(cmd)?)
+    }
+}
@@ -0,0 +1,65 @@
+//! Primitives the runners synchronize with, replaced by [loom]'s when the crate is built with
+//! `--cfg loom`, so the model tests in `test.rs` can explore every interleaving of them
+//!
+//! [loom]: https://docs.rs/loom
+
+#[cfg(loom)]
+pub(crate) use loom::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, atomic};
+#[cfg(not(loom))]
+pub(crate) use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, atomic};
+
+#[cfg(loom)]
+pub(crate) use loom::{cell::UnsafeCell, hint};
+#[cfg(not(loom))]
+pub(crate) use std::hint;
+
+/// Loom replays every execution from the start, so keys must be hashed the same in each
+#[cfg(loom)]
+pub(crate) type RandomState = std::hash::BuildHasherDefault<std::hash::DefaultHasher>;
+#[cfg(not(loom))]
+pub(crate) use std::hash::RandomState;
+
+pub(crate) mod thread {
+    #[cfg(loom)]
+    pub(crate) use loom::thread::{Builder, JoinHandle, Thread, current, park, yield_now};
+    #[cfg(not(loom))]
+    pub(crate) use std::thread::{
+        Builder, JoinHandle, Thread, current, park, park_timeout, yield_now,
+    };
+
+    /// Loom has no clock, so a timed out park is just a spurious wakeup
+    #[cfg(loom)]
+    pub(crate) fn park_timeout(_: std::time::Duration) {
+        park();
+    }
+
+    /// Loom can't tell if a thread is done without joining it, so joining is always allowed to
+    /// block
+    #[cfg(loom)]
+    pub(crate) fn is_finished<T>(_: &JoinHandle<T>) -> bool {
+        true
+    }
+
+    #[cfg(not(loom))]
+    pub(crate) fn is_finished<T>(thread: &JoinHandle<T>) -> bool {
+        thread.is_finished()
+    }
+}
+
+/// [`std::cell::UnsafeCell`] with [loom]'s API, so loom can check every access
+///
+/// [loom]: https://docs.rs/loom
+#[cfg(not(loom))]
+#[derive(Debug)]
+pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) const fn new(t: T) -> Self {
+        Self(std::cell::UnsafeCell::new(t))
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
@@ -1,7 +1,8 @@
+#![allow(clippy::missing_errors_doc)]
 use crate as supera;
 use supera::CommandRunner;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MathAction {
     Sub(i32, i32),
     Stop,
@@ -63,7 +64,7 @@ mod queue {
             for _ in 0..COUNT {
                 outs.push(q.recv().unwrap());
             }
-        })?;
+        });
         assert_eq!(outs, vec![1; COUNT]);
         for r in rs {
             r?;
@@ -71,6 +72,30 @@ mod queue {
         Ok(())
     }
 
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn pool_iterators() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+        let rs = unsafe { supera::queue_pool::PoolQueueAPI::<MathAction, 2>::new() };
+        assert!(rs.recv_timeout(Duration::from_millis(10)).is_err());
+        for i in 0..100 {
+            rs.send(MathAction::Sub(i, 0))?;
+        }
+        let mut outs: Vec<_> = rs.iter().take(50).collect();
+        while outs.len() < 100 {
+            outs.extend(rs.drain());
+        }
+        outs.sort_unstable();
+        assert_eq!(outs, (0..100).collect::<Vec<_>>());
+        assert_eq!(rs.try_iter().count(), 0);
+        for r in rs.close() {
+            r?;
+        }
+        Ok(())
+    }
+
     #[test]
     /// # Panics
     /// The runner can panic.
@@ -92,11 +117,603 @@ mod queue {
         let rs = unsafe { supera::queue_pool::PoolQueueAPI::<MathAction, 3>::new() };
         rs.send(MathAction::Sub(3, 2))?;
         rs.recv()?;
-        for r in rs.close()? {
+        for r in rs.close() {
+            r?;
+        }
+        Ok(())
+    }
+}
+
+mod envelope {
+    use super::*;
+    use supera::queue_envelope::{EnvelopePoolAPI, WorkerId};
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Each runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn pool_provenance() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: i32 = 10_000;
+        let mut sent = std::collections::HashMap::new();
+        let mut outs = Vec::new();
+        let rs = EnvelopePoolAPI::<MathAction, 4>::scope(|q| {
+            for i in 0..COUNT {
+                let id = q.send(MathAction::Sub(i, 1)).unwrap();
+                sent.insert(id, i - 1);
+            }
+            for _ in 0..COUNT {
+                outs.push(q.recv().unwrap());
+            }
+        });
+        assert_eq!(outs.len(), sent.len());
+        for env in outs {
+            assert!(env.worker < WorkerId(4));
+            assert_eq!(sent.remove(&env.id), Some(env.result));
+        }
+        for (i, r) in rs.into_iter().enumerate() {
+            assert_eq!(r?.worker(), WorkerId(i));
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn submitter_ids() -> Result<(), Box<dyn std::error::Error>> {
+        use supera::channel::Crossbeam;
+        let pool = unsafe { EnvelopePoolAPI::<MathAction, 2, Crossbeam>::new() };
+        let sub = pool.submitter();
+        let first = pool.send(MathAction::Sub(3, 1))?;
+        let second = std::thread::spawn(move || sub.send(MathAction::Sub(5, 1)))
+            .join()
+            .unwrap()?;
+        assert_ne!(first, second);
+        let mut results = [pool.recv()?, pool.recv()?].map(|e| (e.id, e.result));
+        results.sort();
+        assert_eq!(results, [(first, 2), (second, 4)]);
+        let sub = pool.submitter();
+        assert!(pool.close().is_clean());
+        assert!(sub.send(MathAction::Sub(1, 1)).is_err());
+        Ok(())
+    }
+}
+
+mod submit {
+    use super::*;
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn pool_submitters() -> Result<(), Box<dyn std::error::Error>> {
+        let q = unsafe { supera::queue_pool::PoolQueueAPI::<MathAction, 2>::new() };
+        let sub = q.submitter();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let sub = sub.clone();
+                s.spawn(move || {
+                    for _ in 0..1_000 {
+                        sub.send(MathAction::Sub(2, 1)).unwrap();
+                    }
+                });
+            }
+        });
+        for _ in 0..4_000 {
+            assert_eq!(q.recv()?, 1);
+        }
+        for r in q.close() {
+            r?;
+        }
+        assert!(sub.send(MathAction::Sub(2, 1)).is_err());
+        Ok(())
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn oneshot_submitter() -> Result<(), Box<dyn std::error::Error>> {
+        let q = unsafe { supera::oneshot_single::OneShotAPI::<MathAction>::new() };
+        let sub = q.submitter();
+        let link = std::thread::spawn(move || sub.send(MathAction::Sub(5, 1)).unwrap());
+        assert_eq!(link.join().expect("submitter thread").recv()?, 4);
+        let sub = q.submitter();
+        q.close()?;
+        let supera::submit::Closed(cmd) = sub.send(MathAction::Sub(5, 1)).unwrap_err();
+        assert!(matches!(cmd, MathAction::Sub(5, 1)));
+        Ok(())
+    }
+}
+
+mod detached {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use supera::detached::{DetachedAPI, ErrorSink};
+
+    #[derive(Debug)]
+    enum Checked {
+        Div(i32, i32),
+        Stop,
+    }
+
+    impl supera::SimpleStop for Checked {
+        fn make_stop_command() -> Self {
+            Checked::Stop
+        }
+    }
+
+    impl supera::Command for Checked {
+        type Result = Result<i32, String>;
+        fn execute(self) -> supera::ActionResult<Self::Result> {
+            match self {
+                Self::Div(a, b) => supera::ActionResult::Normal(
+                    a.checked_div(b).ok_or_else(|| format!("{a} / {b}")),
+                ),
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Each runner can panic.
+    #[test]
+    fn discard() -> Result<(), Box<dyn std::error::Error>> {
+        let rs = DetachedAPI::<MathAction, 3>::scope(|q| {
+            for _ in 0..10_000 {
+                q.send(MathAction::Sub(2, 1)).unwrap();
+            }
+        });
+        for r in rs {
+            r?;
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Each runner can panic.
+    #[test]
+    fn error_sink() -> Result<(), Box<dyn std::error::Error>> {
+        static ERRORS: AtomicUsize = AtomicUsize::new(0);
+        let sink = ErrorSink(|_: String| {
+            ERRORS.fetch_add(1, Ordering::Relaxed);
+        });
+        let q = unsafe { DetachedAPI::<Checked, 2>::with_sink(sink, &supera::RunnerConfig::new()) };
+        for i in 0..1_000 {
+            q.send(Checked::Div(i, i % 4))?;
+        }
+        for r in q.close() {
+            r?;
+        }
+        assert_eq!(ERRORS.load(Ordering::Relaxed), 250);
+        Ok(())
+    }
+}
+
+mod pipeline {
+    use super::*;
+    use supera::pipeline::PipelineBuilder;
+
+    fn two_stages() -> PipelineBuilder<i32, i32> {
+        PipelineBuilder::new(16)
+            .stage(2, 16, |i| MathAction::Sub(i, 1))
+            .stage(3, 8, |r| MathAction::Sub(r, 1))
+    }
+
+    /// # Panics
+    /// Each runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn backpressure() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: i32 = 10_000;
+        let drained = two_stages().scope(|p| {
+            std::thread::scope(|s| {
+                s.spawn(|| {
+                    for i in 0..COUNT {
+                        p.send(i).unwrap();
+                    }
+                });
+                let mut outs: Vec<_> = (0..COUNT).map(|_| p.recv().unwrap()).collect();
+                outs.sort_unstable();
+                assert_eq!(outs, (-2..COUNT - 2).collect::<Vec<_>>());
+            });
+        });
+        assert!(drained.outputs.is_empty());
+        for stage in drained.stages {
+            for r in stage {
+                r?;
+            }
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// Each runner can panic.
+    /// Sending the messages can panic.
+    #[test]
+    fn close_drains() -> Result<(), Box<dyn std::error::Error>> {
+        let p = unsafe { two_stages().build() };
+        for i in 0..30 {
+            p.send(i)?;
+        }
+        let mut drained = p.close();
+        drained.outputs.sort_unstable();
+        assert_eq!(drained.outputs, (-2..28).collect::<Vec<_>>());
+        assert_eq!(
+            drained.stages.iter().map(Vec::len).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct ThreadName;
+
+    impl supera::Command for ThreadName {
+        type Result = String;
+        fn execute(self) -> supera::ActionResult<String> {
+            let name = std::thread::current().name().map(String::from);
+            supera::ActionResult::Normal(name.unwrap_or_default())
+        }
+    }
+
+    /// # Panics
+    /// Each runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn stage_config() -> Result<(), Box<dyn std::error::Error>> {
+        let config = supera::RunnerConfig::new().name("named");
+        let drained = PipelineBuilder::new(4)
+            .stage(1, 4, |i| MathAction::Sub(i, 1))
+            .stage_with_config(2, 4, &config, |_| ThreadName)
+            .scope(|p| {
+                p.send(1).unwrap();
+                assert!(p.recv().unwrap().starts_with("named-"));
+            });
+        for stage in drained.stages {
+            for r in stage {
+                r?;
+            }
+        }
+        Ok(())
+    }
+}
+
+mod join {
+    use super::*;
+    use std::time::Duration;
+    use supera::join::{RaceError, join_all, race_with_timeout, select_any};
+    use supera::oneshot_pool::OneShotPoolAPI;
+
+    #[derive(Debug)]
+    enum Sleep {
+        For(u64),
+        Stop,
+    }
+
+    impl supera::SimpleStop for Sleep {
+        fn make_stop_command() -> Self {
+            Sleep::Stop
+        }
+    }
+
+    impl supera::Command for Sleep {
+        type Result = u64;
+        fn execute(self) -> supera::ActionResult<u64> {
+            match self {
+                Self::For(ms) => {
+                    std::thread::sleep(Duration::from_millis(ms));
+                    supera::ActionResult::Normal(ms)
+                }
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn scatter_gather() -> Result<(), Box<dyn std::error::Error>> {
+        let runners = OneShotPoolAPI::<MathAction, 4>::scope(|q| {
+            let links = (0..1_000).map(|i| q.send(MathAction::Sub(i, 1)).unwrap());
+            let outs: Vec<_> = join_all(links).into_iter().map(Result::unwrap).collect();
+            assert_eq!(outs, (-1..999).collect::<Vec<_>>());
+        });
+        for r in runners {
+            r?;
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn first_wins() -> Result<(), Box<dyn std::error::Error>> {
+        let runners = OneShotPoolAPI::<Sleep, 3>::scope(|q| {
+            let mut links: Vec<_> = [300, 10, 150]
+                .into_iter()
+                .map(|ms| q.send(Sleep::For(ms)).unwrap())
+                .collect();
+            assert_eq!(
+                select_any(&mut links).map(|(i, r)| (i, r.unwrap())),
+                Some((1, 10))
+            );
+            assert_eq!(
+                race_with_timeout(&mut links, Duration::from_millis(20)).err(),
+                Some(RaceError::Timeout)
+            );
+            assert_eq!(
+                race_with_timeout(&mut links, Duration::MAX).map(|(i, r)| (i, r.unwrap())),
+                Ok((1, 150))
+            );
+            assert_eq!(
+                select_any(&mut links).map(|(i, r)| (i, r.unwrap())),
+                Some((0, 300))
+            );
+            assert!(select_any(&mut links).is_none());
+            assert_eq!(
+                race_with_timeout(&mut links, Duration::from_millis(20)).err(),
+                Some(RaceError::Empty)
+            );
+        });
+        for r in runners {
+            r?;
+        }
+        Ok(())
+    }
+}
+
+mod select {
+    use super::*;
+    use std::time::Duration;
+    use supera::{Select, SelectError};
+
+    #[derive(Debug, PartialEq)]
+    enum From {
+        Single(i32),
+        Pool(i32),
+    }
+
+    /// # Panics
+    /// Runner managers can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn two_managers() -> Result<(), Box<dyn std::error::Error>> {
+        let single = unsafe { supera::queue_single::SingleQueueAPI::<MathAction>::new() };
+        let pool = unsafe { supera::queue_pool::PoolQueueAPI::<MathAction, 2>::new() };
+        let mut sel = Select::new()
+            .recv(&single, From::Single)
+            .recv(&pool, From::Pool)
+            .timeout(Duration::from_millis(50));
+        assert_eq!(sel.wait(), Err(SelectError::Timeout));
+        single.send(MathAction::Sub(1, 0))?;
+        assert_eq!(sel.wait(), Ok(From::Single(1)));
+        pool.send(MathAction::Sub(2, 0))?;
+        assert_eq!(sel.wait(), Ok(From::Pool(2)));
+        drop(sel);
+        single.close()?;
+        for r in pool.close() {
+            r?;
+        }
+        Ok(())
+    }
+}
+
+mod keyed {
+    use super::*;
+    use supera::RunnerConfig;
+    use supera::limit::Limit;
+    use supera::queue_keyed::KeyedPoolAPI;
+
+    /// # Panics
+    /// Each runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn per_key_order() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: i32 = 20_000;
+        const KEYS: i32 = 13;
+        let mut q = unsafe { KeyedPoolAPI::<MathAction>::new(4) };
+        let mut outs = Vec::with_capacity(COUNT as usize);
+        for i in 0..COUNT {
+            if i == COUNT / 2 {
+                assert!(q.resize(7).is_clean());
+                assert_eq!(q.workers(), 7);
+            }
+            q.send(&(i % KEYS), MathAction::Sub(i, 0))?;
+        }
+        for _ in 0..COUNT {
+            outs.push(q.recv()?);
+        }
+        for key in 0..KEYS {
+            let seq: Vec<_> = outs.iter().filter(|&&v| v % KEYS == key).collect();
+            assert!(seq.is_sorted());
+        }
+        for r in q.close() {
+            r?;
+        }
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    enum Tagged {
+        Limited(i32),
+        Free(i32),
+        Stop,
+    }
+
+    impl supera::SimpleStop for Tagged {
+        fn make_stop_command() -> Self {
+            Self::Stop
+        }
+    }
+
+    impl supera::Command for Tagged {
+        type Result = i32;
+        fn execute(self) -> supera::ActionResult<i32> {
+            match self {
+                Self::Limited(i) | Self::Free(i) => supera::ActionResult::Normal(i),
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+        fn class(&self) -> Option<&'static str> {
+            match self {
+                Self::Limited(_) => Some("limited"),
+                _ => None,
+            }
+        }
+    }
+
+    /// Commands over their limit don't let the ones behind them overtake them
+    ///
+    /// # Panics
+    /// Each runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn per_key_order_limited() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: i32 = 20_000;
+        const KEYS: i32 = 13;
+        let config = RunnerConfig::new().limit("limited", Limit::new().max_in_flight(1));
+        let q = unsafe { KeyedPoolAPI::<Tagged>::with_config(4, config) };
+        for i in 0..COUNT {
+            let cmd = if i % 3 == 0 {
+                Tagged::Limited(i)
+            } else {
+                Tagged::Free(i)
+            };
+            q.send(&(i % KEYS), cmd)?;
+        }
+        let outs: Vec<_> = (0..COUNT).map(|_| q.recv()).collect::<Result<_, _>>()?;
+        for key in 0..KEYS {
+            let seq: Vec<_> = outs.iter().filter(|&&v| v % KEYS == key).collect();
+            assert!(seq.is_sorted());
+        }
+        for r in q.close() {
             r?;
         }
         Ok(())
     }
+
+    /// Submitters keep each key in order while the pool is resized
+    ///
+    /// # Panics
+    /// Each runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn submitters() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: i32 = 5_000;
+        const KEYS: i32 = 10;
+        let mut q = unsafe { KeyedPoolAPI::<MathAction>::new(3) };
+        let sub = q.submitter();
+        std::thread::scope(|s| {
+            for t in 0..2 {
+                let sub = sub.clone();
+                s.spawn(move || {
+                    // Each thread has it's own keys
+                    for i in 0..COUNT {
+                        let key = t * KEYS / 2 + i % (KEYS / 2);
+                        sub.send(&key, MathAction::Sub(i * KEYS + key, 0)).unwrap();
+                    }
+                });
+            }
+            assert!(q.resize(5).is_clean());
+            assert!(q.resize(2).is_clean());
+        });
+        let outs: Vec<_> = (0..2 * COUNT).map(|_| q.recv()).collect::<Result<_, _>>()?;
+        for key in 0..KEYS {
+            let seq: Vec<_> = outs.iter().filter(|&&v| v % KEYS == key).collect();
+            assert_eq!(seq.len(), (2 * COUNT / KEYS) as usize);
+            assert!(seq.is_sorted());
+        }
+        assert!(q.close().is_clean());
+        assert!(sub.send(&0, MathAction::Sub(1, 1)).is_err());
+        Ok(())
+    }
+}
+
+mod actor {
+    use super::*;
+    use supera::actor::{Actor, ActorHandle};
+
+    #[derive(Debug, Default)]
+    struct Counter {
+        total: i64,
+        seen: usize,
+    }
+
+    impl Actor for Counter {
+        type Msg = i64;
+        type Reply = i64;
+        fn handle(&mut self, msg: i64) -> i64 {
+            self.total += msg;
+            self.seen += 1;
+            self.total
+        }
+    }
+
+    /// # Panics
+    /// The actor can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn tell_and_ask() -> Result<(), Box<dyn std::error::Error>> {
+        let counter = ActorHandle::scope(Counter::default(), |r| {
+            std::thread::scope(|s| {
+                for _ in 0..4 {
+                    let r = r.clone();
+                    s.spawn(move || {
+                        for _ in 0..1_000 {
+                            r.tell(1).unwrap();
+                        }
+                    });
+                }
+            });
+            assert_eq!(r.ask(10).unwrap().recv().unwrap(), 4_010);
+        })?;
+        assert_eq!(counter.total, 4_010);
+        assert_eq!(counter.seen, 4_001);
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending the messages can panic.
+    #[test]
+    fn stopped_actor() -> Result<(), Box<dyn std::error::Error>> {
+        let handle = unsafe { ActorHandle::spawn(Counter::default()) };
+        let r = handle.actor_ref();
+        r.tell(3)?;
+        assert_eq!(handle.stop()?.total, 3);
+        assert!(r.tell(1).is_err());
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending the messages can panic.
+    #[test]
+    fn runs_on_manager() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+        let abandoned = Arc::new(AtomicUsize::new(0));
+        let count = abandoned.clone();
+        let config = supera::RunnerConfig::new().on_abandoned(move |_| {
+            count.fetch_add(1, Ordering::SeqCst);
+        });
+        let handle = unsafe { ActorHandle::spawn_with_config(Counter::default(), &config) };
+        let r = handle.actor_ref();
+        r.tell(2)?;
+        drop(r.ask(3)?);
+        assert_eq!(r.ask(0)?.recv()?, 5);
+        let Ok(counter) = handle.stop_timeout(Duration::from_secs(5)) else {
+            panic!("the actor didn't stop in time");
+        };
+        assert_eq!(counter.seen, 3);
+        // Only the reply that was asked for and dropped is abandoned
+        assert_eq!(abandoned.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
 }
 
 mod oneshot {
@@ -132,7 +749,7 @@ mod oneshot {
                 let r = mr.recv().unwrap();
                 assert_eq!(r, 1);
             }
-        })?;
+        });
         for r in runners {
             r?;
         }
@@ -140,20 +757,114 @@ mod oneshot {
     }
 
     /// # Panics
-    /// The runner can panic.
+    /// Runner manager can panic on close.
+    /// Each runner can panic.
     /// Sending and receiving the messages can panic.
     #[test]
-    fn single_manual_close() -> Result<(), Box<dyn std::error::Error>> {
-        use supera::oneshot_single::OneShotAPI;
-        const COUNT: usize = 2_500;
-        let q = unsafe { OneShotAPI::new() };
-        for _ in 0..COUNT {
-            let ma = MathAction::Sub(2, 1);
-            let mr = q.send(ma).unwrap();
-            let r = mr.recv().unwrap();
-            assert_eq!(r, 1);
-        }
-        q.close()?;
+    fn pool_callbacks() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: i32 = 5_000;
+        let (tx, rx) = std::sync::mpsc::channel();
+        let runners = supera::oneshot_pool::OneShotPoolAPI::<MathAction, 4>::scope(|q| {
+            for i in 0..COUNT {
+                let tx = tx.clone();
+                q.send_with_callback(MathAction::Sub(i, 0), move |r| tx.send(r).unwrap())
+                    .unwrap();
+            }
+        });
+        for r in runners {
+            r?;
+        }
+        drop(tx);
+        let mut outs: Vec<_> = rx.iter().collect();
+        outs.sort_unstable();
+        assert_eq!(outs, (0..COUNT).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Receiving the messages can panic.
+    #[test]
+    fn batched_links() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+        let runners = supera::oneshot_pool::OneShotPoolAPI::<MathAction, 2>::scope(|q| {
+            for batch in 0..100 {
+                let links: Vec<_> = (0..10)
+                    .map(|i| q.send(MathAction::Sub(batch, i)).unwrap())
+                    .collect();
+                for (i, link) in (0..).zip(links) {
+                    let out = link.recv_timeout(Duration::from_secs(1)).unwrap();
+                    assert_eq!(out, batch - i);
+                    assert!(link.try_recv().is_err());
+                }
+            }
+        });
+        for r in runners {
+            r?;
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// The runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn abandoned_links() -> Result<(), Box<dyn std::error::Error>> {
+        use supera::oneshot_single::OneShotAPI;
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = supera::RunnerConfig::new().on_abandoned(move |rst| {
+            tx.send(*rst.downcast::<i32>().unwrap()).unwrap();
+        });
+        let q = unsafe { OneShotAPI::<MathAction>::with_config(&config) };
+        // Holds the runner until every link is dropped
+        let (release, held) = std::sync::mpsc::channel::<()>();
+        q.send_with_callback(MathAction::Sub(0, 0), move |_| held.recv().unwrap())?;
+        for i in 0..3 {
+            drop(q.send(MathAction::Sub(i, 0))?);
+        }
+        release.send(())?;
+        assert_eq!(q.send(MathAction::Sub(2, 1))?.recv()?, 1);
+        let runner = q.close()?;
+        assert_eq!(runner.abandoned(), 3);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2]);
+        Ok(())
+    }
+
+    /// # Panics
+    /// A callback panics on purpose.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn panicking_callback() -> Result<(), Box<dyn std::error::Error>> {
+        use supera::oneshot_single::OneShotAPI;
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = supera::RunnerConfig::new().on_abandoned(move |payload| {
+            tx.send(*payload.downcast::<&str>().unwrap()).unwrap();
+        });
+        let q = unsafe { OneShotAPI::<MathAction>::with_config(&config) };
+        q.send_with_callback(MathAction::Sub(1, 0), |_| panic!("callback broke"))?;
+        // The only runner is still there
+        assert_eq!(q.send(MathAction::Sub(2, 1))?.recv()?, 1);
+        let runner = q.close()?;
+        assert_eq!(runner.abandoned(), 1);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["callback broke"]);
+        Ok(())
+    }
+
+    /// # Panics
+    /// The runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn single_manual_close() -> Result<(), Box<dyn std::error::Error>> {
+        use supera::oneshot_single::OneShotAPI;
+        const COUNT: usize = 2_500;
+        let q = unsafe { OneShotAPI::new() };
+        for _ in 0..COUNT {
+            let ma = MathAction::Sub(2, 1);
+            let mr = q.send(ma).unwrap();
+            let r = mr.recv().unwrap();
+            assert_eq!(r, 1);
+        }
+        q.close()?;
         Ok(())
     }
 
@@ -172,9 +883,1224 @@ mod oneshot {
             let r = mr.recv().unwrap();
             assert_eq!(r, 1);
         }
-        for r in q.close()? {
+        for r in q.close() {
+            r?;
+        }
+        Ok(())
+    }
+}
+
+mod config {
+    use super::*;
+
+    #[derive(Debug)]
+    pub enum ThreadName {
+        Get,
+        Stop,
+    }
+
+    impl supera::SimpleStop for ThreadName {
+        fn make_stop_command() -> Self {
+            ThreadName::Stop
+        }
+    }
+
+    impl supera::Command for ThreadName {
+        type Result = Option<String>;
+        fn execute(self) -> supera::ActionResult<Self::Result> {
+            match self {
+                Self::Get => {
+                    supera::ActionResult::Normal(std::thread::current().name().map(String::from))
+                }
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+    }
+
+    /// # Panics
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn default_names() -> Result<(), Box<dyn std::error::Error>> {
+        supera::queue_single::SingleQueueAPI::<ThreadName>::scope(|q| {
+            q.send(ThreadName::Get).unwrap();
+            assert_eq!(q.recv().unwrap().as_deref(), Some("supera-queue-0"));
+        })?;
+        Ok(())
+    }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn named_pool() -> Result<(), Box<dyn std::error::Error>> {
+        use supera::oneshot_pool::OneShotPoolAPI;
+        let config = supera::RunnerConfig::new()
+            .name("worker")
+            .stack_size(256 * 1024);
+        let runners = OneShotPoolAPI::<ThreadName, 3>::scope_config(&config, |q| {
+            let names: Vec<_> = (0..64)
+                .map(|_| q.send(ThreadName::Get).unwrap().recv().unwrap().unwrap())
+                .collect();
+            assert!(names.iter().all(|n| n.starts_with("worker-")));
+        });
+        for r in runners {
             r?;
         }
         Ok(())
     }
+
+    /// # Panics
+    /// Runner manager can panic on close.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn invalid_affinity() {
+        use supera::queue_pool::PoolQueueAPI;
+        let config = supera::RunnerConfig::new().affinity([usize::MAX]);
+        let q = unsafe { PoolQueueAPI::<ThreadName, 2>::with_config(&config) };
+        q.send(ThreadName::Get).unwrap();
+        assert!(q.recv().is_err());
+        // Every runner is still joined, even though none could take the stop command
+        let report = q.close();
+        assert_eq!(report.failed().count(), 2);
+        assert!(
+            report
+                .failed()
+                .all(|(_, e)| matches!(e, supera::error::EventLoopError::Setup(..)))
+        );
+    }
+}
+
+mod error {
+    use super::*;
+    use supera::CloseOutcome;
+    use supera::error::{CloseError, EventLoopError, SendError};
+
+    #[derive(Debug, PartialEq)]
+    enum Fragile {
+        Fine(i32),
+        Break,
+        Stop,
+    }
+
+    impl supera::SimpleStop for Fragile {
+        fn make_stop_command() -> Self {
+            Self::Stop
+        }
+    }
+
+    impl supera::Command for Fragile {
+        type Result = i32;
+        fn execute(self) -> supera::ActionResult<i32> {
+            match self {
+                Self::Fine(n) => supera::ActionResult::Normal(n),
+                Self::Break => panic!("fragile command broke"),
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+    }
+
+    /// # Panics
+    /// The runner panics on purpose.
+    #[test]
+    fn single_recovers_command() -> Result<(), Box<dyn std::error::Error>> {
+        let rs = unsafe { supera::queue_single::SingleQueueAPI::<Fragile>::new() };
+        rs.send(Fragile::Break)?;
+        // Only disconnected once the runner is gone
+        assert!(rs.recv().is_err());
+        let Err(SendError(cmd)) = rs.send(Fragile::Fine(1)) else {
+            panic!("a stopped runner accepted a command");
+        };
+        assert_eq!(cmd, Fragile::Fine(1));
+        // The runner is still joined, so it's panic is reported
+        let Err(CloseError::Worker(EventLoopError::Panic(payload))) = rs.close() else {
+            panic!("a panicked runner closed cleanly");
+        };
+        assert_eq!(payload.downcast_ref(), Some(&"fragile command broke"));
+        Ok(())
+    }
+
+    /// # Panics
+    /// The runner panics on purpose.
+    #[test]
+    fn keyed_results_end() -> Result<(), Box<dyn std::error::Error>> {
+        let mut q = unsafe { supera::queue_keyed::KeyedPoolAPI::<Fragile>::new(1) };
+        q.send(&0, Fragile::Fine(1))?;
+        q.send(&0, Fragile::Break)?;
+        // Ends since the runner that held the result queue is gone
+        assert_eq!(q.iter().collect::<Vec<_>>(), [1]);
+        assert_eq!(q.resize(2).panicked().count(), 1);
+        q.send(&0, Fragile::Fine(2))?;
+        assert_eq!(q.recv()?, 2);
+        assert!(q.close().is_clean());
+        Ok(())
+    }
+
+    /// # Panics
+    /// One runner panics on purpose.
+    #[test]
+    fn pool_reports_panic() -> Result<(), Box<dyn std::error::Error>> {
+        let rs = unsafe { supera::oneshot_pool::OneShotPoolAPI::<Fragile, 2>::new() };
+        assert!(rs.send(Fragile::Break)?.recv().is_err());
+        assert_eq!(rs.send(Fragile::Fine(2))?.recv()?, 2);
+        let report = rs.close();
+        assert_eq!(report.panicked().count(), 1);
+        assert_eq!(report.clean().count(), 1);
+        assert!(report.failed().next().is_none());
+        assert!(matches!(
+            report.into_result(),
+            Err(CloseError::Worker(EventLoopError::Panic(..)))
+        ));
+        Ok(())
+    }
+
+    /// Once every runner panicked the stop commands can't be sent, but each runner is still
+    /// reported
+    ///
+    /// # Panics
+    /// Every runner panics on purpose.
+    #[test]
+    fn pool_reports_every_panic() -> Result<(), Box<dyn std::error::Error>> {
+        let rs = unsafe { supera::queue_pool::PoolQueueAPI::<Fragile, 2>::new() };
+        rs.send(Fragile::Break)?;
+        rs.send(Fragile::Break)?;
+        assert!(rs.recv().is_err());
+        let report = rs.close();
+        assert_eq!(
+            report.panicked().map(|(i, _)| i).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(report.into_errors().len(), 2);
+        Ok(())
+    }
+}
+
+mod close {
+    use super::*;
+    use crossbeam_channel as mpmc;
+    use std::time::Duration;
+    use supera::CloseTimeout;
+
+    #[derive(Debug)]
+    enum Hang {
+        /// Blocks the runner until the sender is used or dropped
+        Until(mpmc::Receiver<()>),
+        Stop,
+    }
+
+    impl supera::SimpleStop for Hang {
+        fn make_stop_command() -> Self {
+            Self::Stop
+        }
+    }
+
+    impl supera::Command for Hang {
+        type Result = ();
+        fn execute(self) -> supera::ActionResult<()> {
+            match self {
+                Self::Until(rx) => {
+                    let _ = rx.recv();
+                    supera::ActionResult::Normal(())
+                }
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+    }
+
+    /// # Panics
+    /// Sending and joining can panic.
+    #[test]
+    fn stuck_runner() -> Result<(), Box<dyn std::error::Error>> {
+        let pool = unsafe { supera::queue_pool::PoolQueueAPI::<Hang, 2>::new() };
+        let (release, held) = mpmc::bounded(1);
+        pool.send(Hang::Until(held))?;
+        let report = pool.close_timeout(Duration::from_millis(50));
+        assert!(!report.is_clean());
+        assert_eq!(report.stopped.len(), 1);
+        assert!(report.stopped[0].1.is_ok());
+        assert_eq!(report.unfinished.len(), 1);
+        let stuck = report.unfinished.into_iter().next().unwrap();
+        assert_ne!(stuck.index(), report.stopped[0].0);
+        assert!(!stuck.is_finished());
+        // It takes the stop command left in the queue once it's released
+        release.send(())?;
+        stuck.join()?;
+        Ok(())
+    }
+
+    /// Crossbeam's channel with room for a single command
+    struct OneSlot;
+
+    impl supera::channel::Channel for OneSlot {
+        type Sender<T: Send + 'static> = mpmc::Sender<T>;
+        type Receiver<T: Send + 'static> = mpmc::Receiver<T>;
+        fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+            mpmc::bounded(1)
+        }
+    }
+
+    impl supera::channel::SharedChannel for OneSlot {
+        fn share<T: Send + 'static>(recv: &Self::Receiver<T>) -> Self::Receiver<T> {
+            recv.clone()
+        }
+    }
+
+    /// A submitter waiting on a full queue doesn't hold back the close
+    ///
+    /// # Panics
+    /// Sending and joining can panic.
+    #[test]
+    fn full_queue() -> Result<(), Box<dyn std::error::Error>> {
+        let pool = unsafe { supera::queue_pool::PoolQueueAPI::<Hang, 1, OneSlot>::new() };
+        let (release, held) = mpmc::bounded(1);
+        pool.send(Hang::Until(held.clone()))?;
+        // Only sent once the runner took the first one, so the queue is full
+        pool.send(Hang::Until(held))?;
+        let submitter = pool.submitter();
+        let waiting = std::thread::spawn(move || submitter.send(Hang::Stop).is_err());
+        std::thread::sleep(Duration::from_millis(20));
+        let start = std::time::Instant::now();
+        let report = pool.close_timeout(Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(report.unfinished.len(), 1);
+        assert!(waiting.join().unwrap());
+        drop(release);
+        for stuck in report.unfinished {
+            // It's queue is gone without a stop command, since there was no room for one
+            assert!(stuck.join().is_err());
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn in_time() -> Result<(), Box<dyn std::error::Error>> {
+        let q = unsafe { supera::oneshot_single::OneShotAPI::<MathAction>::new() };
+        assert_eq!(q.send(MathAction::Sub(2, 1))?.recv()?, 1);
+        let report = q.close_timeout(Duration::from_secs(5));
+        assert!(report.is_clean());
+        assert_eq!(report.stopped.len(), 1);
+        Ok(())
+    }
+
+    /// # Panics
+    /// Joining can panic.
+    #[test]
+    fn no_deadline() {
+        // Too long to ever be reached, so it waits for every runner
+        let single = unsafe { supera::queue_single::SingleQueueAPI::<MathAction>::new() };
+        assert!(single.close_timeout(Duration::MAX).is_clean());
+        let pool = unsafe { supera::oneshot_pool::OneShotPoolAPI::<MathAction, 2>::new() };
+        assert!(pool.close_timeout(Duration::MAX).is_clean());
+    }
+}
+
+#[cfg(feature = "signal")]
+mod shutdown {
+    use super::*;
+    use std::time::Duration;
+    use supera::queue_pool::PoolQueueAPI;
+    use supera::shutdown::{self, Mode};
+
+    /// Both are in one test, since the registry is global
+    ///
+    /// # Panics
+    /// Sending, receiving and shutting down can panic.
+    #[test]
+    fn registry_and_signals() -> Result<(), Box<dyn std::error::Error>> {
+        let pool = shutdown::register("pool", Mode::Drain, unsafe {
+            PoolQueueAPI::<MathAction, 2>::new()
+        });
+        let single = shutdown::register("single", Mode::Timeout(Duration::from_secs(5)), unsafe {
+            supera::oneshot_single::OneShotAPI::<MathAction>::new()
+        });
+        pool.send(MathAction::Sub(2, 1)).unwrap()?;
+        let results = pool.results().unwrap();
+        assert_eq!(results.recv()?, 1);
+        assert_eq!(single.send(MathAction::Sub(3, 1)).unwrap()?.recv()?, 2);
+        assert_eq!(shutdown::registered(), 2);
+
+        // Waiting for a result doesn't hold back the shutdown
+        let waiting = std::thread::spawn(move || results.recv().is_err());
+        let reports = shutdown::shutdown_all();
+        assert!(waiting.join().unwrap());
+        let names: Vec<_> = reports.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["single", "pool"]);
+        assert!(reports.iter().all(shutdown::Report::is_clean));
+        assert_eq!(
+            pool.send(MathAction::Sub(2, 1)).err(),
+            Some(MathAction::Sub(2, 1))
+        );
+        assert!(single.unregister().is_none());
+
+        #[cfg(target_os = "linux")]
+        {
+            let (tx, rx) = std::sync::mpsc::channel();
+            shutdown::handle_signals(move |sig, reports| tx.send((sig, reports)).unwrap())?;
+            assert!(shutdown::handle_signals(|_, _| {}).is_err());
+            let pool = shutdown::register("signaled", Mode::Drain, unsafe {
+                PoolQueueAPI::<MathAction, 2>::new()
+            });
+            // SAFETY: the handler only writes to it's pipe
+            assert_eq!(unsafe { libc::raise(libc::SIGTERM) }, 0);
+            let (sig, reports) = rx.recv_timeout(Duration::from_secs(5))?;
+            assert_eq!(sig, libc::SIGTERM);
+            assert_eq!(reports.len(), 1);
+            assert!(pool.with(|_| ()).is_none());
+            // A second signal would get the default action back
+            // SAFETY: `sigaction` is a plain struct, and it's only written to
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            // SAFETY: see above
+            assert_eq!(
+                unsafe { libc::sigaction(libc::SIGTERM, std::ptr::null(), &raw mut action) },
+                0
+            );
+            assert_eq!(action.sa_sigaction, libc::SIG_DFL);
+        }
+        Ok(())
+    }
+}
+
+mod runtime {
+    use super::*;
+    use supera::RunnerConfig;
+    use supera::oneshot_pool::OneShotPoolAPI;
+    use supera::queue_pool::PoolQueueAPI;
+    use supera::queue_single::SingleQueueAPI;
+    use supera::runtime::{Runtime, RuntimeError};
+
+    /// # Panics
+    /// Spawning, sending and receiving can panic.
+    #[test]
+    fn named_pools() -> Result<(), Box<dyn std::error::Error>> {
+        let config = RunnerConfig::new();
+        let mut rt = Runtime::with_budget(4);
+        rt.spawn::<PoolQueueAPI<MathAction, 2>>("math", &config.clone().name("math"), &[])?
+            .send(MathAction::Sub(2, 1))?;
+        rt.spawn::<OneShotPoolAPI<MathAction, 2>>("links", &config, &["math"])?;
+        assert_eq!(
+            rt.spawn::<SingleQueueAPI<MathAction>>("math", &config, &[])
+                .err(),
+            Some(RuntimeError::Duplicate("math".into()))
+        );
+        assert_eq!(
+            rt.spawn::<SingleQueueAPI<MathAction>>("single", &config, &["db"])
+                .err(),
+            Some(RuntimeError::UnknownDependency("db".into()))
+        );
+        assert_eq!(
+            rt.spawn::<SingleQueueAPI<MathAction>>("single", &config, &[])
+                .err(),
+            Some(RuntimeError::OverBudget {
+                requested: 1,
+                available: 0
+            })
+        );
+
+        let math = rt.get::<PoolQueueAPI<MathAction, 2>>("math").unwrap();
+        assert_eq!(math.recv()?, 1);
+        assert!(rt.get::<SingleQueueAPI<MathAction>>("math").is_none());
+        let links = rt.get_by_type::<OneShotPoolAPI<MathAction, 2>>().unwrap();
+        assert_eq!(links.send(MathAction::Sub(3, 1))?.recv()?, 2);
+
+        let stats = rt.stats();
+        assert_eq!(stats.threads, 4);
+        let names: Vec<_> = stats.pools.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["math", "links"]);
+        // Every result was received, so the runners are done with them
+        for pool in &stats.pools {
+            assert_eq!((pool.received, pool.executed, pool.abandoned), (1, 1, 0));
+        }
+
+        let reports = rt.close();
+        let names: Vec<_> = reports.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["links", "math"]);
+        assert!(reports.iter().all(|r| r.errors.is_empty()));
+        Ok(())
+    }
+
+    /// # Panics
+    /// Spawning can panic.
+    #[test]
+    fn close_order() -> Result<(), Box<dyn std::error::Error>> {
+        let config = RunnerConfig::new();
+        let mut rt = Runtime::new();
+        rt.spawn::<SingleQueueAPI<MathAction>>("db", &config, &[])?;
+        rt.spawn::<SingleQueueAPI<MathAction>>("cache", &config, &[])?;
+        rt.spawn::<SingleQueueAPI<MathAction>>("api", &config, &["db"])?;
+        let reports = rt.close();
+        let names: Vec<_> = reports.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["cache", "api", "db"]);
+        Ok(())
+    }
+}
+
+mod limit {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, RwLock};
+    use std::time::{Duration, Instant};
+    use supera::RunnerConfig;
+    use supera::limit::Limit;
+
+    /// How many slow jobs are in flight and the most there ever were
+    #[derive(Debug, Default)]
+    struct Counts {
+        now: AtomicUsize,
+        max: AtomicUsize,
+        /// Slow jobs only finish while it's not locked
+        open: RwLock<()>,
+    }
+
+    #[derive(Debug)]
+    enum Job {
+        Slow(Arc<Counts>),
+        Fast,
+        Stop,
+    }
+
+    impl supera::SimpleStop for Job {
+        fn make_stop_command() -> Self {
+            Self::Stop
+        }
+    }
+
+    impl supera::Command for Job {
+        type Result = &'static str;
+        fn execute(self) -> supera::ActionResult<&'static str> {
+            match self {
+                Self::Slow(counts) => {
+                    let now = counts.now.fetch_add(1, Ordering::SeqCst) + 1;
+                    counts.max.fetch_max(now, Ordering::SeqCst);
+                    drop(counts.open.read());
+                    counts.now.fetch_sub(1, Ordering::SeqCst);
+                    supera::ActionResult::Normal("slow")
+                }
+                Self::Fast => supera::ActionResult::Normal("fast"),
+                Self::Stop => supera::ActionResult::Stop,
+            }
+        }
+        fn class(&self) -> Option<&'static str> {
+            match self {
+                Self::Slow(..) => Some("slow"),
+                _ => None,
+            }
+        }
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn capped_class() -> Result<(), Box<dyn std::error::Error>> {
+        let config = RunnerConfig::new().limit("slow", Limit::new().max_in_flight(2));
+        let pool = unsafe { supera::queue_pool::PoolQueueAPI::<Job, 4>::with_config(&config) };
+        let counts = Arc::new(Counts::default());
+        let open = counts.open.write().unwrap();
+        for _ in 0..3 {
+            pool.send(Job::Slow(counts.clone()))?;
+        }
+        pool.send(Job::Fast)?;
+        // Two runners hold the slow jobs in flight and a third waits for one of them to be
+        // done, so the last one executes the fast job
+        assert_eq!(pool.recv()?, "fast");
+        while counts.now.load(Ordering::SeqCst) < 2 {
+            std::thread::yield_now();
+        }
+        drop(open);
+        for _ in 0..3 {
+            assert_eq!(pool.recv()?, "slow");
+        }
+        assert_eq!(counts.max.load(Ordering::SeqCst), 2);
+        assert!(pool.close().into_result().is_ok());
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn capped_runner_keeps_receiving() -> Result<(), Box<dyn std::error::Error>> {
+        let config = RunnerConfig::new().limit("slow", Limit::new().max_in_flight(2));
+        let pool = unsafe { supera::queue_pool::PoolQueueAPI::<Job, 3>::with_config(&config) };
+        let counts = Arc::new(Counts::default());
+        let open = counts.open.write().unwrap();
+        for _ in 0..3 {
+            pool.send(Job::Slow(counts.clone()))?;
+        }
+        while counts.now.load(Ordering::SeqCst) < 2 {
+            std::thread::yield_now();
+        }
+        // The third runner put off a slow job, but still executes the fast one
+        pool.send(Job::Fast)?;
+        assert_eq!(pool.recv_timeout(Duration::from_secs(1))?, "fast");
+        drop(open);
+        for _ in 0..3 {
+            assert_eq!(pool.recv()?, "slow");
+        }
+        assert_eq!(counts.max.load(Ordering::SeqCst), 2);
+        assert!(pool.close().into_result().is_ok());
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn rate() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = Limit::new().rate(2, Duration::from_millis(100)).burst(1);
+        let config = RunnerConfig::new().limit("slow", limit);
+        let q = unsafe { supera::oneshot_single::OneShotAPI::<Job>::with_config(&config) };
+        let counts = Arc::new(Counts::default());
+        let start = Instant::now();
+        let links = (0..3)
+            .map(|_| q.send(Job::Slow(counts.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Commands put off are executed before the runner stops
+        assert!(q.close().is_ok());
+        for link in links {
+            assert_eq!(link.recv()?, "slow");
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        Ok(())
+    }
+}
+
+mod generic {
+    use super::*;
+    use supera::submit::Submit;
+    use supera::{CloseOutcome, SendOutcome};
+
+    fn subtract_all(to: &dyn Submit<MathAction, Ack = ()>, count: i32) -> usize {
+        (0..count)
+            .filter(|&i| to.submit(MathAction::Sub(i, 1)).is_ok())
+            .count()
+    }
+
+    /// Works with any manager, closing it and counting what went wrong
+    fn run<R>(count: i32) -> (usize, usize)
+    where
+        R: CommandRunner<Cmd = MathAction>,
+    {
+        let rs = unsafe { R::new() };
+        let acks: Vec<_> = (0..count)
+            .filter_map(|i| rs.send(MathAction::Sub(i, 1)).into_result().ok())
+            .collect();
+        let errors = rs.close().into_errors().len();
+        (acks.len(), errors)
+    }
+
+    /// # Panics
+    /// Runner managers can panic on close.
+    #[test]
+    fn any_ordered_manager() {
+        let single = unsafe { supera::queue_single::SingleQueueAPI::<MathAction>::new() };
+        let pool = unsafe { supera::queue_pool::PoolQueueAPI::<MathAction, 2>::new() };
+        let submitter = pool.submitter();
+        let managers: [&dyn Submit<MathAction, Ack = ()>; 3] = [&single, &pool, &submitter];
+        for m in managers {
+            assert_eq!(subtract_all(m, 10), 10);
+        }
+        assert_eq!(single.iter().take(10).count(), 10);
+        assert_eq!(pool.iter().take(20).count(), 20);
+        assert!(single.close().into_errors().is_empty());
+        assert!(pool.close().into_errors().is_empty());
+    }
+
+    /// # Panics
+    /// Runner managers can panic on close.
+    #[test]
+    fn generic_close() {
+        assert_eq!(run::<supera::queue_single::SingleQueueAPI<_>>(5), (5, 0));
+        assert_eq!(run::<supera::oneshot_pool::OneShotPoolAPI<_, 3>>(5), (5, 0));
+        assert_eq!(
+            run::<supera::queue_envelope::EnvelopePoolAPI<_, 2>>(5),
+            (5, 0)
+        );
+    }
+}
+
+mod channel {
+    use super::*;
+    use supera::channel::{Channel, Crossbeam, SharedChannel};
+    #[cfg(feature = "flume")]
+    use supera::detached::DetachedAPI;
+    use supera::queue_keyed::KeyedPoolAPI;
+    use supera::queue_pool::PoolQueueAPI;
+    use supera::queue_single::SingleQueueChanAPI;
+
+    /// # Panics
+    /// Runner managers can panic on close.
+    /// Sending and receiving the messages can panic.
+    fn sum_on<Ch: SharedChannel>() -> Result<i32, Box<dyn std::error::Error>> {
+        let mut sum = 0;
+        let rs = PoolQueueAPI::<MathAction, 3, Ch>::scope(|q| {
+            for i in 0..100 {
+                q.send(MathAction::Sub(i, 0)).unwrap();
+            }
+            sum = (0..100).map(|_| q.recv().unwrap()).sum();
+        });
+        for r in rs {
+            r?;
+        }
+        Ok(sum)
+    }
+
+    /// # Panics
+    /// Runner managers can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn backends() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(sum_on::<Crossbeam>()?, 4950);
+        #[cfg(feature = "flume")]
+        assert_eq!(sum_on::<supera::channel::Flume>()?, 4950);
+
+        let q = unsafe { SingleQueueChanAPI::<MathAction, Crossbeam>::new() };
+        q.send(MathAction::Sub(5, 3))?;
+        assert_eq!(q.recv()?, 2);
+        q.close()?;
+
+        // Each runner of a keyed pool has it's own queue, so it can use a single consumer ring
+        let keyed = unsafe { KeyedPoolAPI::<MathAction, supera::channel::Spsc<8>>::new(2) };
+        for i in 0..100 {
+            keyed.send(&i, MathAction::Sub(i, 0))?;
+        }
+        assert_eq!(
+            (0..100).map(|_| keyed.recv()).sum::<Result<i32, _>>()?,
+            4950
+        );
+        assert!(keyed.close().is_clean());
+
+        #[cfg(feature = "flume")]
+        {
+            let rs = DetachedAPI::<MathAction, 2, supera::channel::Flume>::scope(|q| {
+                q.send(MathAction::Sub(2, 1)).unwrap();
+            });
+            assert!(rs.is_clean());
+        }
+        Ok(())
+    }
+
+    /// # Panics
+    /// The runner can panic.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn spsc_values() -> Result<(), Box<dyn std::error::Error>> {
+        const COUNT: usize = 500_000;
+        let mut outs = Vec::with_capacity(COUNT);
+        // A small ring, so the manager often waits for the runner
+        SingleQueueChanAPI::<MathAction, supera::channel::Spsc<64>>::scope(|q| {
+            for _ in 0..COUNT {
+                q.send(MathAction::Sub(2, 1)).unwrap();
+            }
+            for _ in 0..COUNT {
+                outs.push(q.recv().unwrap());
+            }
+        })?;
+        assert_eq!(outs, vec![1; COUNT]);
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn spsc_disconnect() {
+        use std::time::Duration;
+        use supera::{ChanRecv, ChanSend};
+        let (tx, rx) = supera::channel::Spsc::<2>::channel::<String>();
+        tx.send_t("a".into()).unwrap();
+        tx.send_t("b".into()).unwrap();
+        assert!(matches!(
+            tx.try_send_t("c".into()),
+            Err(crossbeam_channel::TrySendError::Full(_))
+        ));
+        let sender = std::thread::spawn(move || tx.send_t("c".into()).is_ok());
+        assert_eq!(rx.recv_t().unwrap(), "a");
+        assert!(sender.join().unwrap());
+        assert_eq!(rx.recv_t().unwrap(), "b");
+        assert_eq!(rx.recv_t().unwrap(), "c");
+        // The sender is gone once it's thread is joined
+        assert!(matches!(
+            rx.recv_timeout_t(Duration::from_millis(10)),
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected)
+        ));
+        let (tx, rx) = supera::channel::Spsc::<2>::channel::<String>();
+        assert!(rx.recv_timeout_t(Duration::from_millis(10)).is_err());
+        tx.send_t("left over".into()).unwrap();
+        drop(rx);
+        assert!(tx.send_t("d".into()).is_err());
+    }
+
+    /// # Panics
+    /// Receiving can panic.
+    #[test]
+    fn try_and_timeout() {
+        use std::time::Duration;
+        use supera::{ChanRecv, ChanSend};
+        let (tx, rx) = supera::channel::Std::channel::<i32>();
+        assert!(rx.try_recv_t().is_err());
+        tx.try_send_t(1).unwrap();
+        assert_eq!(rx.recv_timeout_t(Duration::from_millis(10)).unwrap(), 1);
+        assert!(rx.recv_timeout_t(Duration::from_millis(10)).is_err());
+    }
+
+    struct Plain;
+    struct PlainSender<T>(std::sync::mpsc::Sender<T>);
+    struct PlainReceiver<T>(std::sync::mpsc::Receiver<T>);
+
+    impl<T> supera::ChanSend<T> for PlainSender<T> {
+        type Err = std::sync::mpsc::SendError<T>;
+        fn send_t(&self, t: T) -> Result<(), Self::Err> {
+            self.0.send(t)
+        }
+    }
+
+    impl<T> supera::ChanRecv<T> for PlainReceiver<T> {
+        type Err = std::sync::mpsc::RecvError;
+        fn recv_t(&self) -> Result<T, Self::Err> {
+            self.0.recv()
+        }
+    }
+
+    impl Channel for Plain {
+        type Sender<T: Send + 'static> = PlainSender<T>;
+        type Receiver<T: Send + 'static> = PlainReceiver<T>;
+        fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+            let (tx, rx) = std::sync::mpsc::channel();
+            (PlainSender(tx), PlainReceiver(rx))
+        }
+    }
+
+    /// A channel that only sends and receives still backs a manager
+    ///
+    /// # Panics
+    /// Runner manager can panic on close.
+    /// Sending and receiving the messages can panic.
+    #[test]
+    fn plain_channel() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+        use supera::CloseTimeout;
+        let q = unsafe { supera::queue_single::SingleQueueChanAPI::<MathAction, Plain>::new() };
+        q.send(MathAction::Sub(3, 1))?;
+        assert_eq!(q.recv()?, 2);
+        assert!(q.close_timeout(Duration::from_secs(1)).is_clean());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "testing")]
+mod testing {
+    use super::*;
+    use std::time::Duration;
+    use supera::testing::{Event, Fault, MockRunner, Step};
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn stepped_with_delay() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = MockRunner::stepped();
+        mock.fault(1, Fault::Delay(Duration::from_secs(5)));
+        mock.send(MathAction::Sub(3, 1))?;
+        mock.send(MathAction::Sub(5, 1))?;
+        mock.send(MathAction::Sub(9, 1))?;
+        assert!(mock.recv().is_err());
+        assert_eq!(mock.step(), Step::Ran);
+        assert_eq!(mock.recv()?, 2);
+        // The delayed command holds back the one after it
+        assert_eq!(mock.step(), Step::Waiting(Duration::from_secs(5)));
+        mock.advance(Duration::from_secs(5));
+        assert_eq!(mock.pending(), 2);
+        assert_eq!(mock.run_until_idle(), 2);
+        assert_eq!(mock.recv()?, 4);
+        assert_eq!(mock.recv()?, 8);
+        assert_eq!(mock.step(), Step::Idle);
+        let log = mock.close()?;
+        assert_eq!(
+            log.executed(),
+            [
+                MathAction::Sub(3, 1),
+                MathAction::Sub(5, 1),
+                MathAction::Sub(9, 1)
+            ]
+        );
+        assert_eq!(log.0.last(), Some(&Event::Stopped(MathAction::Stop)));
+        Ok(())
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn inline_faults() {
+        let mock = MockRunner::inline();
+        mock.fault(0, Fault::Send);
+        mock.fault(1, Fault::Recv);
+        mock.fault(3, Fault::Panic);
+        assert!(mock.send(MathAction::Sub(1, 1)).is_err());
+        mock.send(MathAction::Sub(2, 1)).unwrap();
+        mock.send(MathAction::Sub(3, 1)).unwrap();
+        assert!(mock.recv().is_err());
+        assert_eq!(mock.recv().unwrap(), 2);
+        mock.send(MathAction::Sub(4, 1)).unwrap();
+        // The runner is gone after panicking
+        assert!(mock.send(MathAction::Sub(5, 1)).is_err());
+        assert_eq!(
+            mock.log().0[..3],
+            [
+                Event::Rejected(MathAction::Sub(1, 1)),
+                Event::Sent(MathAction::Sub(2, 1)),
+                Event::Executed(MathAction::Sub(2, 1)),
+            ]
+        );
+        assert!(
+            mock.log()
+                .0
+                .contains(&Event::Panicked(MathAction::Sub(4, 1)))
+        );
+        assert!(matches!(
+            mock.close(),
+            Err(supera::error::CloseError::Worker(
+                supera::error::EventLoopError::Panic(_)
+            ))
+        ));
+    }
+}
+
+/// Run with `RUSTFLAGS="--cfg loom" cargo test --release --lib model`, the other tests can't run
+/// on loom's primitives
+#[cfg(loom)]
+mod model {
+    use super::*;
+    use crossbeam_channel as mpmc;
+    use loom::sync::{Arc, Condvar, Mutex};
+    use std::collections::VecDeque;
+    use std::time::Duration;
+    use supera::channel::{Channel, SharedChannel, Spsc};
+    use supera::error::SendError;
+    use supera::{ChanRecv, ChanSend, CloseOutcome, ResultQueue};
+
+    /// Explores every interleaving with up to 3 preemptions, unless `LOOM_MAX_PREEMPTIONS` is
+    /// set
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound.get_or_insert(3);
+        builder.check(f);
+    }
+
+    #[derive(Default)]
+    struct Ends<T> {
+        items: VecDeque<T>,
+        senders: usize,
+        receivers: usize,
+    }
+
+    /// Unbounded queue built on loom's primitives, so the pools and submitters can be modeled
+    struct Queue<T> {
+        ends: Mutex<Ends<T>>,
+        ready: Condvar,
+    }
+
+    impl<T> Queue<T> {
+        /// # Panics
+        /// Panics if the lock is poisoned.
+        fn ends(&self) -> loom::sync::MutexGuard<'_, Ends<T>> {
+            self.ends.lock().unwrap()
+        }
+    }
+
+    struct QueueSender<T>(Arc<Queue<T>>);
+    struct QueueReceiver<T>(Arc<Queue<T>>);
+
+    impl<T> Clone for QueueSender<T> {
+        fn clone(&self) -> Self {
+            self.0.ends().senders += 1;
+            Self(self.0.clone())
+        }
+    }
+
+    impl<T> Drop for QueueSender<T> {
+        fn drop(&mut self) {
+            self.0.ends().senders -= 1;
+            self.0.ready.notify_all();
+        }
+    }
+
+    impl<T> Drop for QueueReceiver<T> {
+        fn drop(&mut self) {
+            self.0.ends().receivers -= 1;
+        }
+    }
+
+    impl<T> ChanSend<T> for QueueSender<T> {
+        type Err = SendError<T>;
+        fn send_t(&self, t: T) -> Result<(), Self::Err> {
+            self.try_send_t(t).map_err(|e| SendError(e.into_inner()))
+        }
+        fn try_send_t(&self, t: T) -> Result<(), mpmc::TrySendError<T>> {
+            let mut ends = self.0.ends();
+            if ends.receivers == 0 {
+                return Err(mpmc::TrySendError::Disconnected(t));
+            }
+            ends.items.push_back(t);
+            drop(ends);
+            self.0.ready.notify_one();
+            Ok(())
+        }
+    }
+
+    impl<T> ChanRecv<T> for QueueReceiver<T> {
+        type Err = mpmc::RecvError;
+        fn recv_t(&self) -> Result<T, Self::Err> {
+            let mut ends = self.0.ends();
+            loop {
+                if let Some(t) = ends.items.pop_front() {
+                    return Ok(t);
+                }
+                if ends.senders == 0 {
+                    return Err(mpmc::RecvError);
+                }
+                ends = self.0.ready.wait(ends).unwrap();
+            }
+        }
+        fn try_recv_t(&self) -> Result<T, mpmc::TryRecvError> {
+            let mut ends = self.0.ends();
+            match ends.items.pop_front() {
+                Some(t) => Ok(t),
+                None if ends.senders == 0 => Err(mpmc::TryRecvError::Disconnected),
+                None => Err(mpmc::TryRecvError::Empty),
+            }
+        }
+        /// Loom has no clock, so it never times out
+        fn recv_timeout_t(&self, _: Duration) -> Result<T, mpmc::RecvTimeoutError> {
+            self.recv_t()
+                .map_err(|_| mpmc::RecvTimeoutError::Disconnected)
+        }
+    }
+
+    struct Loom;
+
+    impl Channel for Loom {
+        type Sender<T: Send + 'static> = QueueSender<T>;
+        type Receiver<T: Send + 'static> = QueueReceiver<T>;
+        fn channel<T: Send + 'static>() -> (Self::Sender<T>, Self::Receiver<T>) {
+            let queue = Arc::new(Queue {
+                ends: Mutex::new(Ends {
+                    items: VecDeque::new(),
+                    senders: 1,
+                    receivers: 1,
+                }),
+                ready: Condvar::new(),
+            });
+            (QueueSender(queue.clone()), QueueReceiver(queue))
+        }
+    }
+
+    impl SharedChannel for Loom {
+        fn share<T: Send + 'static>(recv: &Self::Receiver<T>) -> Self::Receiver<T> {
+            recv.0.ends().receivers += 1;
+            QueueReceiver(recv.0.clone())
+        }
+    }
+
+    /// # Panics
+    /// Sending and receiving can panic.
+    #[test]
+    fn spsc_full_then_closed() {
+        model(|| {
+            let (tx, rx) = Spsc::<1>::channel::<i32>();
+            loom::thread::spawn(move || {
+                tx.send_t(1).unwrap();
+                tx.send_t(2).unwrap();
+            });
+            assert_eq!(rx.recv_t().unwrap(), 1);
+            assert_eq!(rx.recv_t().unwrap(), 2);
+            assert!(rx.recv_t().is_err());
+        });
+    }
+
+    /// Every command a submitter manages to send is executed before the runner stops
+    ///
+    /// # Panics
+    /// Sending and closing can panic.
+    #[test]
+    fn single_submitter_races_close() {
+        model(|| {
+            let q = unsafe { supera::queue_single::SingleQueueChanAPI::<MathAction, Loom>::new() };
+            let submitter = q.submitter();
+            let sender = loom::thread::spawn(move || submitter.send(MathAction::Sub(2, 1)).is_ok());
+            let results = q.results().clone();
+            q.close().unwrap();
+            let sent = sender.join().unwrap();
+            assert_eq!(results.try_iter().count(), usize::from(sent));
+        });
+    }
+
+    /// # Panics
+    /// Sending and closing can panic.
+    #[test]
+    fn spsc_single_ordered() {
+        model(|| {
+            let q =
+                unsafe { supera::queue_single::SingleQueueChanAPI::<MathAction, Spsc<1>>::new() };
+            q.send(MathAction::Sub(2, 1)).unwrap();
+            q.send(MathAction::Sub(3, 1)).unwrap();
+            let results = q.results().clone();
+            q.close().unwrap();
+            assert_eq!(results.try_iter().collect::<Vec<_>>(), [1, 2]);
+        });
+    }
+
+    /// Each runner of a pool takes one of the stop commands
+    ///
+    /// # Panics
+    /// Sending and closing can panic.
+    #[test]
+    fn pool_stops_every_runner() {
+        model(|| {
+            let q = unsafe { supera::queue_pool::PoolQueueAPI::<MathAction, 2, Loom>::new() };
+            q.send(MathAction::Sub(2, 1)).unwrap();
+            let results = q.results().clone();
+            assert!(q.close().into_errors().is_empty());
+            assert_eq!(results.try_iter().collect::<Vec<_>>(), [1]);
+        });
+    }
+
+    /// # Panics
+    /// Sending, receiving and closing can panic.
+    #[test]
+    fn oneshot_pool_links() {
+        model(|| {
+            let q = unsafe { supera::oneshot_pool::OneShotPoolAPI::<MathAction, 2, Loom>::new() };
+            let first = q.send(MathAction::Sub(2, 1)).unwrap();
+            let second = q.send(MathAction::Sub(3, 1)).unwrap();
+            assert!(q.close().into_errors().is_empty());
+            assert_eq!(second.try_recv().unwrap(), 2);
+            assert_eq!(first.try_recv().unwrap(), 1);
+        });
+    }
+
+    /// A link dropped before the runner sends it's result doesn't stop the runner
+    ///
+    /// Loom can't see inside `oneshot`, so the runner sending the result while the link is
+    /// dropped isn't explored, only that the runner keeps going either way.
+    ///
+    /// # Panics
+    /// Sending and closing can panic.
+    #[test]
+    fn oneshot_dropped_link() {
+        model(|| {
+            let q = unsafe { supera::oneshot_single::OneShotChanAPI::<MathAction, Loom>::new() };
+            drop(q.send(MathAction::Sub(2, 1)).unwrap());
+            let link = q.send(MathAction::Sub(3, 1)).unwrap();
+            let runner = q.close().unwrap();
+            assert!(runner.abandoned() <= 1);
+            assert_eq!(link.try_recv().unwrap(), 2);
+        });
+    }
+
+    /// Every result is either received, still queued when the receiver was dropped, or
+    /// abandoned, and the runner keeps going after the result queue is dropped
+    ///
+    /// # Panics
+    /// Sending, receiving and joining can panic.
+    #[test]
+    fn queue_dropped_results() {
+        model(|| {
+            let (send_cmd, recv_cmd) = Loom::channel();
+            let (send_res, recv_res) = Loom::channel::<i32>();
+            let queue = send_res.0.clone();
+            let runner = crate::queue::QueueRunner::spawn(
+                &supera::RunnerConfig::default(),
+                "supera-model",
+                0,
+                recv_cmd,
+                send_res,
+            );
+            let receiver = loom::thread::spawn(move || recv_res.recv_t().unwrap());
+            send_cmd.send_t(MathAction::Sub(2, 1)).unwrap();
+            send_cmd.send_t(MathAction::Sub(3, 1)).unwrap();
+            assert_eq!(receiver.join().unwrap(), 1);
+            send_cmd.send_t(MathAction::Stop).unwrap();
+            let runner = runner.join().unwrap().unwrap();
+            let queued = queue.ends().items.len();
+            assert_eq!(1 + queued + usize::try_from(runner.abandoned()).unwrap(), 2);
+        });
+    }
+
+    /// Every command a submitter manages to send gets an envelope before the runners stop
+    ///
+    /// # Panics
+    /// Sending and closing can panic.
+    #[test]
+    fn envelope_submitter_races_close() {
+        model(|| {
+            let q =
+                unsafe { supera::queue_envelope::EnvelopePoolAPI::<MathAction, 2, Loom>::new() };
+            let submitter = q.submitter();
+            let sender = loom::thread::spawn(move || submitter.send(MathAction::Sub(3, 1)).ok());
+            let first = q.send(MathAction::Sub(2, 1)).unwrap();
+            let results = q.results().clone();
+            assert!(q.close().into_errors().is_empty());
+            let second = sender.join().unwrap();
+            let mut ids: Vec<_> = results.try_iter().map(|e| e.id).collect();
+            ids.sort();
+            let mut sent: Vec<_> = [Some(first), second].into_iter().flatten().collect();
+            sent.sort();
+            assert_eq!(ids, sent);
+        });
+    }
+
+    /// Commands with the same key keep their order, also across a resize
+    ///
+    /// # Panics
+    /// Sending, resizing and closing can panic.
+    #[test]
+    fn keyed_order_across_resize() {
+        model(|| {
+            let mut q = unsafe { supera::queue_keyed::KeyedPoolAPI::<MathAction, Loom>::new(2) };
+            assert_ne!(q.worker_for("a"), q.worker_for("b"));
+            q.send("a", MathAction::Sub(2, 1)).unwrap();
+            q.send("b", MathAction::Sub(5, 1)).unwrap();
+            assert!(q.resize(1).into_errors().is_empty());
+            q.send("a", MathAction::Sub(3, 1)).unwrap();
+            let results = q.results().clone();
+            assert!(q.close().into_errors().is_empty());
+            let results: Vec<_> = results.try_iter().collect();
+            assert_eq!(
+                results.iter().filter(|&&r| r != 4).collect::<Vec<_>>(),
+                [&1, &2]
+            );
+            assert!(results.contains(&4));
+        });
+    }
+
+    struct Count(Arc<loom::sync::atomic::AtomicUsize>);
+
+    impl supera::detached::ResultSink<i32> for Count {
+        fn accept(&self, _: i32) {
+            self.0.fetch_add(1, loom::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Every command a submitter manages to send reaches the sink before the runners stop
+    ///
+    /// # Panics
+    /// Sending and closing can panic.
+    #[test]
+    fn detached_submitter_races_close() {
+        model(|| {
+            let count = Arc::new(loom::sync::atomic::AtomicUsize::new(0));
+            let q = unsafe {
+                supera::detached::DetachedAPI::<MathAction, 2, Loom>::with_sink(
+                    Count(count.clone()),
+                    &supera::RunnerConfig::default(),
+                )
+            };
+            let submitter = q.submitter();
+            let sender = loom::thread::spawn(move || submitter.send(MathAction::Sub(3, 1)).is_ok());
+            q.send(MathAction::Sub(2, 1)).unwrap();
+            assert!(q.close().into_errors().is_empty());
+            let sent = sender.join().unwrap();
+            assert_eq!(
+                count.load(loom::sync::atomic::Ordering::Relaxed),
+                1 + usize::from(sent)
+            );
+        });
+    }
 }
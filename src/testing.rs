@@ -0,0 +1,318 @@
+//! A deterministic stand-in for the managers, to test code that uses them without threads or
+//! timing
+use crate::error::{CloseError, EventLoopError, SendError};
+use crate::{ActionResult, CmdRst, Command, CommandRunner, RunnerConfig, Stopped};
+use crossbeam_channel as mpmc;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+/// When a [`MockRunner`] executes the commands it's sent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// As soon as they're sent, or once [`MockRunner::advance`] reaches a delayed one
+    #[default]
+    Inline,
+    /// Only on [`MockRunner::step`] or [`MockRunner::run_until_idle`]
+    Stepped,
+}
+
+/// Fault injected into a command, given the order it's sent in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Sending it fails, giving the command back
+    Send,
+    /// It's executed, but it's result is lost. [`MockRunner::recv`] fails with
+    /// [`TryRecvError::Disconnected`](mpmc::TryRecvError::Disconnected) in it's place, though
+    /// the results after it can still be received
+    Recv,
+    /// The runner panics instead of executing it, so every command after it fails to be sent
+    Panic,
+    /// It's only executed once the mock's clock moved by this much, holding back every command
+    /// after it
+    Delay(Duration),
+}
+
+/// What happened to a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<Cmd> {
+    Sent(Cmd),
+    /// Sending it failed
+    Rejected(Cmd),
+    Executed(Cmd),
+    /// It's [`ActionResult::Stop`] stopped the runner
+    Stopped(Cmd),
+    Panicked(Cmd),
+}
+
+/// Every [`Event`] of a [`MockRunner`], in the order they happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log<Cmd>(pub Vec<Event<Cmd>>);
+
+impl<Cmd: Clone> Log<Cmd> {
+    /// Commands that were executed, in order
+    #[must_use]
+    pub fn executed(&self) -> Vec<Cmd> {
+        self.0
+            .iter()
+            .filter_map(|e| match e {
+                Event::Executed(cmd) => Some(cmd.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl<Cmd, Rst> Stopped<Rst> for Log<Cmd> {
+    fn failures(self) -> Vec<EventLoopError<Rst>> {
+        Vec::new()
+    }
+}
+
+/// Result of [`MockRunner::step`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// A command was executed
+    Ran,
+    /// The next command is delayed for this long
+    Waiting(Duration),
+    /// No command is queued
+    Idle,
+    /// The runner stopped, by a stop command or a panic
+    Stopped,
+}
+
+struct Queued<Cmd> {
+    cmd: Cmd,
+    ready_at: Duration,
+    faults: Vec<Fault>,
+}
+
+struct State<Cmd: Command> {
+    mode: Mode,
+    /// How many commands were sent, including the rejected ones
+    sent: usize,
+    faults: HashMap<usize, Vec<Fault>>,
+    queue: VecDeque<Queued<Cmd>>,
+    /// `None` for results that fail to be received
+    results: VecDeque<Option<CmdRst<Cmd>>>,
+    log: Vec<Event<Cmd>>,
+    now: Duration,
+    stopped: bool,
+    failure: Option<EventLoopError<CmdRst<Cmd>>>,
+}
+
+/// Single threaded [`CommandRunner`] with ordered results, like
+/// [`SingleQueueAPI`](crate::queue_single::SingleQueueAPI)
+///
+/// Commands are executed on the thread that sends them, at the points given by it's [`Mode`],
+/// and time only passes by [`MockRunner::advance`]. Every command is logged, and it's
+/// [`CommandRunner::close`] gives back the [`Log`].
+pub struct MockRunner<Cmd: Command> {
+    state: RefCell<State<Cmd>>,
+}
+
+impl<Cmd> MockRunner<Cmd>
+where
+    Cmd: Command + Clone,
+{
+    #[must_use]
+    pub fn with_mode(mode: Mode) -> Self {
+        Self {
+            state: RefCell::new(State {
+                mode,
+                sent: 0,
+                faults: HashMap::new(),
+                queue: VecDeque::new(),
+                results: VecDeque::new(),
+                log: Vec::new(),
+                now: Duration::ZERO,
+                stopped: false,
+                failure: None,
+            }),
+        }
+    }
+
+    #[must_use]
+    pub fn inline() -> Self {
+        Self::with_mode(Mode::Inline)
+    }
+
+    #[must_use]
+    pub fn stepped() -> Self {
+        Self::with_mode(Mode::Stepped)
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        self.state.borrow_mut().mode = mode;
+    }
+
+    /// Injects `fault` into the `index`th command sent, counting from 0
+    pub fn fault(&self, index: usize, fault: Fault) {
+        let mut state = self.state.borrow_mut();
+        state.faults.entry(index).or_default().push(fault);
+    }
+
+    /// Executes the next queued command, if it's not delayed
+    pub fn step(&self) -> Step {
+        let mut state = self.state.borrow_mut();
+        if state.stopped {
+            return Step::Stopped;
+        }
+        let Some(next) = state.queue.pop_front() else {
+            return Step::Idle;
+        };
+        if let Some(left) = next.ready_at.checked_sub(state.now)
+            && !left.is_zero()
+        {
+            state.queue.push_front(next);
+            return Step::Waiting(left);
+        }
+        drop(state);
+
+        let logged = next.cmd.clone();
+        let outcome = if next.faults.contains(&Fault::Panic) {
+            Err(Box::new("injected panic") as Box<dyn std::any::Any + Send>)
+        } else {
+            panic::catch_unwind(AssertUnwindSafe(|| next.cmd.execute()))
+        };
+
+        let mut state = self.state.borrow_mut();
+        match outcome {
+            Ok(ActionResult::Normal(rst)) => {
+                state.log.push(Event::Executed(logged));
+                let lost = next.faults.contains(&Fault::Recv);
+                state.results.push_back((!lost).then_some(rst));
+                Step::Ran
+            }
+            Ok(ActionResult::Stop) => {
+                state.log.push(Event::Stopped(logged));
+                state.stopped = true;
+                Step::Stopped
+            }
+            Err(payload) => {
+                state.log.push(Event::Panicked(logged));
+                state.stopped = true;
+                state.failure = Some(EventLoopError::Panic(payload));
+                Step::Stopped
+            }
+        }
+    }
+
+    /// Executes every queued command that's not delayed, returns how many were
+    pub fn run_until_idle(&self) -> usize {
+        let mut ran = 0;
+        while self.step() == Step::Ran {
+            ran += 1;
+        }
+        ran
+    }
+
+    /// Moves the clock forward, executing the commands that are due if it's [`Mode::Inline`]
+    pub fn advance(&self, by: Duration) {
+        let mode = {
+            let mut state = self.state.borrow_mut();
+            state.now += by;
+            state.mode
+        };
+        if mode == Mode::Inline {
+            self.run_until_idle();
+        }
+    }
+
+    /// How far the clock moved since the mock was created
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.state.borrow().now
+    }
+
+    /// How many commands are queued
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.state.borrow().queue.len()
+    }
+
+    /// # Errors
+    /// Fails with [`TryRecvError::Empty`](mpmc::TryRecvError::Empty) if no result is available,
+    /// or with [`TryRecvError::Disconnected`](mpmc::TryRecvError::Disconnected) if the next one
+    /// was lost by [`Fault::Recv`]. Unlike a real runner's queue, it doesn't mean no more results
+    /// will come.
+    pub fn recv(&self) -> Result<CmdRst<Cmd>, mpmc::TryRecvError> {
+        match self.state.borrow_mut().results.pop_front() {
+            Some(Some(rst)) => Ok(rst),
+            Some(None) => Err(mpmc::TryRecvError::Disconnected),
+            None => Err(mpmc::TryRecvError::Empty),
+        }
+    }
+
+    #[must_use]
+    pub fn log(&self) -> Log<Cmd> {
+        Log(self.state.borrow().log.clone())
+    }
+}
+
+impl<Cmd> CommandRunner for MockRunner<Cmd>
+where
+    Cmd: Command + Clone,
+{
+    type Cmd = Cmd;
+    type SendAck = Result<(), SendError<Cmd>>;
+    type CloseResult = Result<Log<Cmd>, CloseError<Cmd, CmdRst<Cmd>>>;
+    /// The config is ignored, since no thread is spawned
+    unsafe fn with_config(_config: &RunnerConfig) -> Self {
+        Self::inline()
+    }
+    fn send(&self, cmd: Self::Cmd) -> Self::SendAck {
+        let mut state = self.state.borrow_mut();
+        let index = state.sent;
+        state.sent += 1;
+        let faults = state.faults.remove(&index).unwrap_or_default();
+        if state.stopped || faults.contains(&Fault::Send) {
+            state.log.push(Event::Rejected(cmd.clone()));
+            return Err(SendError(cmd));
+        }
+        let delay = faults
+            .iter()
+            .map(|f| match f {
+                Fault::Delay(d) => *d,
+                _ => Duration::ZERO,
+            })
+            .sum::<Duration>();
+        state.log.push(Event::Sent(cmd.clone()));
+        let ready_at = state.now + delay;
+        state.queue.push_back(Queued {
+            cmd,
+            ready_at,
+            faults,
+        });
+        let mode = state.mode;
+        drop(state);
+        if mode == Mode::Inline {
+            self.run_until_idle();
+        }
+        Ok(())
+    }
+    /// Executes every queued command before the stop command, advancing the clock past their
+    /// delays
+    ///
+    /// If the runner already stopped, no stop command is sent. A runner that panicked fails with
+    /// [`EventLoopError::Panic`], so it's [`Log`] is only kept by [`MockRunner::log`].
+    fn close_with(self, mut s: impl crate::StopRunner<Self::Cmd>) -> Self::CloseResult {
+        if !self.state.borrow().stopped {
+            self.send(s.get())?;
+        }
+        loop {
+            match self.step() {
+                Step::Ran => {}
+                Step::Waiting(left) => self.state.borrow_mut().now += left,
+                Step::Idle | Step::Stopped => break,
+            }
+        }
+        let state = self.state.into_inner();
+        match state.failure {
+            Some(e) => Err(e.into()),
+            None => Ok(Log(state.log)),
+        }
+    }
+}